@@ -0,0 +1,6 @@
+begin;
+
+alter table devices alter column address drop not null;
+alter table devices add column path text null;
+
+commit;
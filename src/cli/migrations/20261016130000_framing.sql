@@ -0,0 +1,6 @@
+begin;
+
+create type device_framing as enum ('tcp', 'rtu');
+alter table devices add column framing device_framing not null default 'tcp';
+
+commit;
@@ -0,0 +1,10 @@
+begin;
+
+create table batch_plans (
+  device_id text primary key not null references devices(id) on delete cascade,
+  threshold int not null,
+  max_quantity int not null,
+  updated timestamp with time zone not null
+);
+
+commit;
@@ -0,0 +1,6 @@
+begin;
+
+alter table devices add column port int null;
+update devices set address = host(address)::inet, port = 502 where address is not null;
+
+commit;
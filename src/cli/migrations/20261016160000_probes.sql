@@ -0,0 +1,16 @@
+begin;
+
+create type probe_result as enum ('matched', 'unsupported', 'undetected', 'unidentified');
+create table probes (
+  id bigserial primary key not null,
+  timestamp timestamp with time zone not null,
+  address inet null,
+  port int null,
+  path text null,
+  framing device_framing not null,
+  slave int null,
+  result probe_result not null,
+  device text null
+);
+
+commit;
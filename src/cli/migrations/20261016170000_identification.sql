@@ -0,0 +1,5 @@
+begin;
+
+alter table devices add column identification jsonb null;
+
+commit;
@@ -0,0 +1,5 @@
+begin;
+
+alter table measurements add column group_name text null;
+
+commit;
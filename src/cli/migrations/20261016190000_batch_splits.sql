@@ -0,0 +1,13 @@
+begin;
+
+create type register_table as enum ('holding', 'input', 'coil', 'discrete');
+create table batch_splits (
+  device_id text not null references devices(id) on delete cascade,
+  register_table register_table not null,
+  address int not null,
+  quantity int not null,
+  updated timestamp with time zone not null,
+  primary key (device_id, register_table, address, quantity)
+);
+
+commit;
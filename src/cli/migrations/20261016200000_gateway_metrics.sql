@@ -0,0 +1,9 @@
+begin;
+
+create table gateway_metrics (
+  gateway text primary key not null,
+  metrics jsonb not null,
+  updated timestamp with time zone not null
+);
+
+commit;
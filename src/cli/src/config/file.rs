@@ -4,6 +4,7 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use crate::service::modbus::{self, RegisterValue};
+use crate::service::network;
 
 // NITPICK: optional values here with #[serde(default = ...)]
 
@@ -15,6 +16,8 @@ pub(crate) struct Hardware {
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Network {
   pub(crate) timeout: Option<u32>,
+  pub(crate) ports: Option<Vec<u16>>,
+  pub(crate) scan: Option<bool>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -32,11 +35,36 @@ pub(crate) enum LogLevel {
   Error,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RegisterTable {
+  Holding,
+  Input,
+  Coil,
+  Discrete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ScaleFactorRegister {
+  Name(String),
+  Address(u16),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct MeasurementRegister {
   pub(crate) name: String,
+  pub(crate) table: Option<RegisterTable>,
   pub(crate) address: u16,
   pub(crate) kind: RegisterKindStorage,
+  pub(crate) scale_factor: Option<ScaleFactorRegister>,
+  pub(crate) group: Option<String>,
+  pub(crate) interval: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MeasurementGroup {
+  pub(crate) interval: Option<u32>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -49,12 +77,60 @@ pub(crate) struct RawRegisterKind {
   pub(crate) length: u16,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BitsRegisterKind {
+  pub(crate) length: Option<u16>,
+  pub(crate) bits: HashMap<String, u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct EnumRegisterKind {
+  pub(crate) length: Option<u16>,
+  pub(crate) labels: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct NumericRegisterKind {
   pub(crate) multiplier: Option<Decimal>,
+  pub(crate) offset: Option<Decimal>,
+  pub(crate) endianness: Option<Endianness>,
+  pub(crate) sentinels: Option<Vec<Decimal>>,
+  pub(crate) missing: Option<MissingValuePolicy>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MissingValuePolicy {
+  Null,
+  Zero,
+  Drop,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Endianness {
+  Abcd,
+  Cdab,
+  Badc,
+  Dcba,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DatetimeFormat {
+  Unix,
+  Iec870,
+  Bcd,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct DatetimeRegisterKind {
+  pub(crate) format: DatetimeFormat,
+  pub(crate) timezone: Option<chrono_tz::Tz>,
+  pub(crate) timestamp: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum RegisterKindStorage {
   U16(NumericRegisterKind),
@@ -65,51 +141,220 @@ pub(crate) enum RegisterKindStorage {
   S64(NumericRegisterKind),
   F32(NumericRegisterKind),
   F64(NumericRegisterKind),
+  Bcd16(NumericRegisterKind),
+  Bcd32(NumericRegisterKind),
+  Bcd64(NumericRegisterKind),
+  Sm16(NumericRegisterKind),
+  Sm32(NumericRegisterKind),
+  Sm64(NumericRegisterKind),
   String(StringRegisterKind),
   Raw(RawRegisterKind),
+  Bits(BitsRegisterKind),
+  Enum(EnumRegisterKind),
+  Datetime(DatetimeRegisterKind),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct DetectRegister {
+  pub(crate) table: Option<RegisterTable>,
   pub(crate) address: u16,
   pub(crate) kind: RegisterKindStorage,
   pub(crate) r#match: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct IdentificationMatch {
+  pub(crate) vendor: Option<String>,
+  pub(crate) product: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct IdRegister {
+  pub(crate) table: Option<RegisterTable>,
   pub(crate) address: u16,
   pub(crate) kind: RegisterKindStorage,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TypedValue {
+  U16(Decimal),
+  U32(Decimal),
+  U64(Decimal),
+  S16(Decimal),
+  S32(Decimal),
+  S64(Decimal),
+  F32(Decimal),
+  F64(Decimal),
+  Bcd16(Decimal),
+  Bcd32(Decimal),
+  Bcd64(Decimal),
+  Sm16(Decimal),
+  Sm32(Decimal),
+  Sm64(Decimal),
+  String(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ValueStorage {
+  Raw(Vec<u16>),
+  Typed(TypedValue),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct ValueRegister {
+  pub(crate) table: Option<RegisterTable>,
   pub(crate) address: u16,
-  pub(crate) value: Vec<u16>,
+  pub(crate) value: ValueStorage,
+  pub(crate) endianness: Option<Endianness>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Framing {
+  Tcp,
+  Rtu,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Hole {
+  pub(crate) table: Option<RegisterTable>,
+  pub(crate) start: u16,
+  pub(crate) end: u16,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Device {
+  pub(crate) framing: Option<Framing>,
+  pub(crate) batch_max_quantity: Option<u16>,
+  #[serde(default)]
+  pub(crate) holes: Vec<Hole>,
+  pub(crate) identification: Option<IdentificationMatch>,
+  pub(crate) interval: Option<u32>,
+  #[serde(default)]
   pub(crate) detect: Vec<DetectRegister>,
   pub(crate) id: Vec<IdRegister>,
   pub(crate) measurement: Vec<MeasurementRegister>,
+  #[serde(default)]
+  pub(crate) groups: HashMap<String, MeasurementGroup>,
   pub(crate) configuration: Vec<ValueRegister>,
   pub(crate) daily: Vec<ValueRegister>,
   pub(crate) nightly: Vec<ValueRegister>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Parity {
+  None,
+  Odd,
+  Even,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Serial {
+  pub(crate) path: String,
+  pub(crate) baud_rate: Option<u32>,
+  pub(crate) data_bits: Option<u8>,
+  pub(crate) parity: Option<Parity>,
+  pub(crate) stop_bits: Option<u8>,
+  pub(crate) delay: Option<u32>,
+  pub(crate) kinds: Option<Vec<String>>,
+  pub(crate) slaves: Option<Slaves>,
+  pub(crate) miss_tolerance: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AddressRange {
+  pub(crate) start: String,
+  pub(crate) end: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SlaveRange {
+  pub(crate) start: u8,
+  pub(crate) end: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Slaves {
+  List(Vec<u8>),
+  Range(SlaveRange),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Gateway {
+  pub(crate) address: String,
+  pub(crate) port: Option<u16>,
+  pub(crate) read_timeout: Option<u32>,
+  pub(crate) slaves: Option<Slaves>,
+  pub(crate) delay: Option<u32>,
+  pub(crate) miss_tolerance: Option<u32>,
+  pub(crate) concurrency: Option<usize>,
+  pub(crate) connections: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StaticDevice {
+  pub(crate) kind: String,
+  pub(crate) address: Option<String>,
+  pub(crate) port: Option<u16>,
+  pub(crate) path: Option<String>,
+  pub(crate) slave: Option<u8>,
+  pub(crate) id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ConcentratorRegister {
+  pub(crate) name: String,
+  pub(crate) address: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ConcentratorUnit {
+  pub(crate) unit: u8,
+  pub(crate) device: String,
+  #[serde(default)]
+  pub(crate) registers: Vec<ConcentratorRegister>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Concentrator {
+  pub(crate) address: Option<String>,
+  pub(crate) port: Option<u16>,
+  pub(crate) max_age: Option<u32>,
+  #[serde(default)]
+  pub(crate) units: Vec<ConcentratorUnit>,
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Modbus {
   pub(crate) read_timeout: Option<u32>,
   pub(crate) batch_threshold: Option<u16>,
+  pub(crate) batch_max_quantity: Option<u16>,
+  pub(crate) adaptive_batching: Option<bool>,
   pub(crate) termination_timeout: Option<u32>,
   pub(crate) congestion_backoff: Option<u32>,
   pub(crate) partial_retries: Option<u32>,
+  pub(crate) queue_capacity: Option<usize>,
+  pub(crate) request_deadline: Option<u32>,
   pub(crate) ping_timeout: Option<u32>,
   pub(crate) tariff_timeout: Option<u32>,
   pub(crate) inactive_timeout: Option<u32>,
   pub(crate) discovery_timeout: Option<u32>,
+  pub(crate) discovery_miss_tolerance: Option<u32>,
+  pub(crate) discovery_concurrency: Option<usize>,
   pub(crate) devices: HashMap<String, Device>,
+  #[serde(default)]
+  pub(crate) serial: Vec<Serial>,
+  #[serde(default)]
+  pub(crate) rtu_over_tcp: Vec<AddressRange>,
+  #[serde(default)]
+  pub(crate) gateways: Vec<Gateway>,
+  #[serde(default)]
+  pub(crate) static_devices: Vec<StaticDevice>,
+  pub(crate) concentrator: Option<Concentrator>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -171,8 +416,34 @@ pub(crate) enum ParseError {
 
   #[error("Failed deserializing config from json")]
   DeserializetionJson(#[from] serde_json::Error),
+
+  #[error("Flag register of {0:?} is longer than 4 words")]
+  FlagLength(String),
+
+  #[error("Value {1} written to {0:?} does not fit its type")]
+  ValueRange(String, Decimal),
+
+  #[error(
+    "Measurement register {1:?} of {0:?} references unknown group {2:?}"
+  )]
+  UnknownGroup(String, String, String),
+
+  #[error("Serial port {0:?} has {1} data bits instead of 5 to 8")]
+  DataBits(String, u8),
+
+  #[error("Serial port {0:?} has {1} stop bits instead of 1 or 2")]
+  StopBits(String, u8),
+
+  #[error("Failed parsing {0} address {1:?}")]
+  Address(&'static str, String),
+
+  #[error("Static device {0:?} has no address or path")]
+  StaticLocation(String),
 }
 
+// NOTE: flag registers decode into a u64
+const MAX_FLAG_LENGTH: u16 = 4;
+
 pub(crate) async fn parse_file(
   location: Option<&str>,
 ) -> Result<Values, ParseError> {
@@ -194,23 +465,241 @@ pub(crate) async fn parse_file(
       Some(_) => return Err(ParseError::InvalidExtension),
     }
   };
+  validate(&values)?;
 
   Ok(values)
 }
 
 pub(crate) async fn parse_json(json: &str) -> Result<Values, ParseError> {
   let parsed = serde_json::from_str::<Values>(json)?;
+  validate(&parsed)?;
 
   Ok(parsed)
 }
 
-pub(crate) fn to_modbus_measurement_register(
-  register: MeasurementRegister,
+// NOTE: rejects what deserializes fine but can't be read or written right
+fn validate(values: &Values) -> Result<(), ParseError> {
+  for (kind, device) in values.modbus.devices.iter() {
+    let register_kinds = device
+      .detect
+      .iter()
+      .map(|register| &register.kind)
+      .chain(device.id.iter().map(|register| &register.kind))
+      .chain(device.measurement.iter().map(|register| &register.kind));
+    for register_kind in register_kinds {
+      validate_register_kind(kind, register_kind)?;
+    }
+    let values = device
+      .configuration
+      .iter()
+      .chain(device.daily.iter())
+      .chain(device.nightly.iter())
+      .filter_map(|register| match &register.value {
+        ValueStorage::Typed(value) => Some(value),
+        ValueStorage::Raw(_) => None,
+      });
+    for value in values {
+      validate_typed_value(kind, value)?;
+    }
+    for register in device.measurement.iter() {
+      if let Some(group) = &register.group {
+        if !device.groups.contains_key(group) {
+          return Err(ParseError::UnknownGroup(
+            kind.clone(),
+            register.name.clone(),
+            group.clone(),
+          ));
+        }
+      }
+    }
+  }
+  for serial in values.modbus.serial.iter() {
+    validate_serial(serial)?;
+  }
+  for gateway in values.modbus.gateways.iter() {
+    validate_address("gateway", &gateway.address)?;
+  }
+  for device in values.modbus.static_devices.iter() {
+    match (&device.path, &device.address) {
+      (Some(_), _) => {}
+      (None, Some(address)) => validate_address("static device", address)?,
+      (None, None) => {
+        return Err(ParseError::StaticLocation(device.kind.clone()));
+      }
+    }
+  }
+  if let Some(address) = values
+    .modbus
+    .concentrator
+    .as_ref()
+    .and_then(|concentrator| concentrator.address.as_ref())
+  {
+    validate_address("concentrator", address)?;
+  }
+
+  Ok(())
+}
+
+fn validate_address(
+  location: &'static str,
+  address: &str,
+) -> Result<(), ParseError> {
+  address
+    .parse::<std::net::IpAddr>()
+    .map(|_| ())
+    .map_err(|_| ParseError::Address(location, address.to_string()))
+}
+
+// NOTE: serial framing falls back to 8N1 so anything else is a typo
+fn validate_serial(serial: &Serial) -> Result<(), ParseError> {
+  match serial.data_bits {
+    Some(data_bits) if !(5..=8).contains(&data_bits) => {
+      return Err(ParseError::DataBits(serial.path.clone(), data_bits));
+    }
+    _ => {}
+  }
+  match serial.stop_bits {
+    Some(stop_bits) if !(1..=2).contains(&stop_bits) => {
+      return Err(ParseError::StopBits(serial.path.clone(), stop_bits));
+    }
+    _ => {}
+  }
+
+  Ok(())
+}
+
+fn validate_register_kind(
+  device: &str,
+  kind: &RegisterKindStorage,
+) -> Result<(), ParseError> {
+  match kind {
+    RegisterKindStorage::Bits(BitsRegisterKind {
+      length: Some(length),
+      ..
+    })
+    | RegisterKindStorage::Enum(EnumRegisterKind {
+      length: Some(length),
+      ..
+    }) if *length > MAX_FLAG_LENGTH => {
+      Err(ParseError::FlagLength(device.to_string()))
+    }
+    _ => Ok(()),
+  }
+}
+
+// NOTE: serialising falls back to zero for values outside of the type
+// range so they get rejected here instead of written to devices
+fn validate_typed_value(
+  device: &str,
+  value: &TypedValue,
+) -> Result<(), ParseError> {
+  let (value, min, max) = match value {
+    TypedValue::U16(value) => (value, 0, i128::from(u16::MAX)),
+    TypedValue::U32(value) => (value, 0, i128::from(u32::MAX)),
+    TypedValue::U64(value) => (value, 0, i128::from(u64::MAX)),
+    TypedValue::S16(value) => {
+      (value, i128::from(i16::MIN), i128::from(i16::MAX))
+    }
+    TypedValue::S32(value) => {
+      (value, i128::from(i32::MIN), i128::from(i32::MAX))
+    }
+    TypedValue::S64(value) => {
+      (value, i128::from(i64::MIN), i128::from(i64::MAX))
+    }
+    TypedValue::Bcd16(value) => (value, 0, 9_999),
+    TypedValue::Bcd32(value) => (value, 0, 99_999_999),
+    TypedValue::Bcd64(value) => (value, 0, 9_999_999_999_999_999),
+    TypedValue::Sm16(value) => (value, -0x7FFF, 0x7FFF),
+    TypedValue::Sm32(value) => (value, -0x7FFF_FFFF, 0x7FFF_FFFF),
+    TypedValue::Sm64(value) => (
+      value,
+      i128::from(i64::MIN.saturating_add(1)),
+      i128::from(i64::MAX),
+    ),
+    TypedValue::F32(_) | TypedValue::F64(_) | TypedValue::String(_) => {
+      return Ok(())
+    }
+  };
+
+  if value.fract().is_zero()
+    && *value >= Decimal::from(min)
+    && *value <= Decimal::from(max)
+  {
+    Ok(())
+  } else {
+    Err(ParseError::ValueRange(device.to_string(), *value))
+  }
+}
+
+// NOTE: scale factor registers referenced only by address get added as
+// measurement registers so they are read in the same poll as their users
+pub(crate) fn to_modbus_measurement_registers(
+  registers: Vec<MeasurementRegister>,
+) -> Vec<modbus::MeasurementRegister<modbus::RegisterKindStorage>> {
+  let mut scale_factors = Vec::new();
+  let mut result = registers
+    .iter()
+    .cloned()
+    .map(|register| {
+      let table = to_modbus_register_table(register.table);
+      let scale_factor = match register.scale_factor {
+        Some(ScaleFactorRegister::Name(name)) => Some(name),
+        Some(ScaleFactorRegister::Address(address)) => Some(
+          match registers.iter().find(|other| {
+            other.address == address
+              && to_modbus_register_table(other.table) == table
+          }) {
+            Some(other) => other.name.clone(),
+            None => {
+              let name = format!("scaleFactor{address}");
+              if !scale_factors.iter().any(
+                |other: &modbus::MeasurementRegister<_>| other.name == name,
+              ) {
+                scale_factors.push(to_modbus_scale_factor_register(
+                  name.clone(),
+                  table,
+                  address,
+                ));
+              }
+              name
+            }
+          },
+        ),
+        None => None,
+      };
+
+      modbus::MeasurementRegister::<modbus::RegisterKindStorage> {
+        table,
+        address: register.address,
+        storage: to_modbus_register_kind(register.kind),
+        name: register.name,
+        scale_factor,
+        internal: false,
+      }
+    })
+    .collect::<Vec<_>>();
+  result.extend(scale_factors);
+  result
+}
+
+fn to_modbus_scale_factor_register(
+  name: String,
+  table: modbus::RegisterTable,
+  address: u16,
 ) -> modbus::MeasurementRegister<modbus::RegisterKindStorage> {
   modbus::MeasurementRegister::<modbus::RegisterKindStorage> {
-    address: register.address,
-    storage: to_modbus_register_kind(register.kind),
-    name: register.name,
+    table,
+    address,
+    storage: modbus::RegisterKindStorage::S16(modbus::NumericRegisterKind {
+      multiplier: None,
+      offset: None,
+      endianness: modbus::Endianness::Abcd,
+      sentinels: Some(vec![Decimal::from(i16::MIN)]),
+      missing: modbus::MissingValuePolicy::Null,
+    }),
+    name,
+    scale_factor: None,
+    internal: true,
   }
 }
 
@@ -218,6 +707,7 @@ pub(crate) fn to_modbus_detect_register(
   register: DetectRegister,
 ) -> modbus::DetectRegister<modbus::RegisterKindStorage> {
   modbus::DetectRegister::<modbus::RegisterKindStorage> {
+    table: to_modbus_register_table(register.table),
     address: register.address,
     storage: to_modbus_register_kind(register.kind),
     r#match: match regex::Regex::new(register.r#match.as_str()) {
@@ -227,10 +717,27 @@ pub(crate) fn to_modbus_detect_register(
   }
 }
 
+pub(crate) fn to_modbus_identification_match(
+  identification: IdentificationMatch,
+) -> modbus::IdentificationMatch {
+  modbus::IdentificationMatch {
+    vendor: identification.vendor.map(to_string_or_regex),
+    product: identification.product.map(to_string_or_regex),
+  }
+}
+
+fn to_string_or_regex(value: String) -> either::Either<String, regex::Regex> {
+  match regex::Regex::new(value.as_str()) {
+    Ok(regex) => either::Either::Right(regex),
+    _ => either::Either::Left(value),
+  }
+}
+
 pub(crate) fn to_modbus_id_register(
   register: IdRegister,
 ) -> modbus::IdRegister<modbus::RegisterKindStorage> {
   modbus::IdRegister::<modbus::RegisterKindStorage> {
+    table: to_modbus_register_table(register.table),
     address: register.address,
     storage: to_modbus_register_kind(register.kind),
   }
@@ -240,11 +747,108 @@ pub(crate) fn to_modbus_value_register(
   register: ValueRegister,
 ) -> modbus::ValueRegister<modbus::RegisterValueStorage> {
   modbus::ValueRegister::<modbus::RegisterValueStorage> {
+    table: to_modbus_register_table(register.table),
     address: register.address,
-    storage: modbus::RegisterValueStorage::Raw(RegisterValue::<_> {
-      value: register.value,
-      timestamp: chrono::Utc::now(),
-    }),
+    storage: to_modbus_value_storage(register.value, register.endianness),
+  }
+}
+
+pub(crate) fn to_modbus_value_storage(
+  value: ValueStorage,
+  endianness: Option<Endianness>,
+) -> modbus::RegisterValueStorage {
+  let timestamp = chrono::Utc::now();
+  let numeric = |value: Decimal| modbus::NumericRegisterValue {
+    value: Some(value),
+    timestamp,
+    endianness: to_modbus_endianness(endianness),
+    missing: modbus::MissingValuePolicy::Zero,
+    multiplier: None,
+    offset: None,
+  };
+
+  match value {
+    ValueStorage::Raw(value) => {
+      modbus::RegisterValueStorage::Raw(RegisterValue::<_> { value, timestamp })
+    }
+    ValueStorage::Typed(TypedValue::U16(value)) => {
+      modbus::RegisterValueStorage::U16(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::U32(value)) => {
+      modbus::RegisterValueStorage::U32(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::U64(value)) => {
+      modbus::RegisterValueStorage::U64(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::S16(value)) => {
+      modbus::RegisterValueStorage::S16(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::S32(value)) => {
+      modbus::RegisterValueStorage::S32(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::S64(value)) => {
+      modbus::RegisterValueStorage::S64(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::F32(value)) => {
+      modbus::RegisterValueStorage::F32(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::F64(value)) => {
+      modbus::RegisterValueStorage::F64(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Bcd16(value)) => {
+      modbus::RegisterValueStorage::Bcd16(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Bcd32(value)) => {
+      modbus::RegisterValueStorage::Bcd32(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Bcd64(value)) => {
+      modbus::RegisterValueStorage::Bcd64(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Sm16(value)) => {
+      modbus::RegisterValueStorage::Sm16(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Sm32(value)) => {
+      modbus::RegisterValueStorage::Sm32(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::Sm64(value)) => {
+      modbus::RegisterValueStorage::Sm64(numeric(value))
+    }
+    ValueStorage::Typed(TypedValue::String(value)) => {
+      modbus::RegisterValueStorage::String(RegisterValue::<_> {
+        value,
+        timestamp,
+      })
+    }
+  }
+}
+
+pub(crate) fn to_modbus_endianness(
+  endianness: Option<Endianness>,
+) -> modbus::Endianness {
+  match endianness {
+    Some(Endianness::Abcd) | None => modbus::Endianness::Abcd,
+    Some(Endianness::Cdab) => modbus::Endianness::Cdab,
+    Some(Endianness::Badc) => modbus::Endianness::Badc,
+    Some(Endianness::Dcba) => modbus::Endianness::Dcba,
+  }
+}
+
+pub(crate) fn to_modbus_hole(hole: Hole) -> modbus::Hole {
+  modbus::Hole {
+    table: to_modbus_register_table(hole.table),
+    start: hole.start,
+    end: hole.end,
+  }
+}
+
+pub(crate) fn to_modbus_register_table(
+  table: Option<RegisterTable>,
+) -> modbus::RegisterTable {
+  match table {
+    Some(RegisterTable::Holding) | None => modbus::RegisterTable::Holding,
+    Some(RegisterTable::Input) => modbus::RegisterTable::Input,
+    Some(RegisterTable::Coil) => modbus::RegisterTable::Coil,
+    Some(RegisterTable::Discrete) => modbus::RegisterTable::Discrete,
   }
 }
 
@@ -252,45 +856,47 @@ pub(crate) fn to_modbus_register_kind(
   register: RegisterKindStorage,
 ) -> modbus::RegisterKindStorage {
   match register {
-    RegisterKindStorage::U16(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::U16(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::U16(kind) => {
+      modbus::RegisterKindStorage::U16(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::U32(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::U32(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::U32(kind) => {
+      modbus::RegisterKindStorage::U32(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::U64(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::U64(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::U64(kind) => {
+      modbus::RegisterKindStorage::U64(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::S16(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::S16(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::S16(kind) => {
+      modbus::RegisterKindStorage::S16(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::S32(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::S32(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::S32(kind) => {
+      modbus::RegisterKindStorage::S32(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::S64(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::S64(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::S64(kind) => {
+      modbus::RegisterKindStorage::S64(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::F32(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::F32(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::F32(kind) => {
+      modbus::RegisterKindStorage::F32(to_modbus_numeric_register_kind(kind))
     }
-    RegisterKindStorage::F64(NumericRegisterKind { multiplier }) => {
-      modbus::RegisterKindStorage::F64(modbus::NumericRegisterKind {
-        multiplier,
-      })
+    RegisterKindStorage::F64(kind) => {
+      modbus::RegisterKindStorage::F64(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Bcd16(kind) => {
+      modbus::RegisterKindStorage::Bcd16(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Bcd32(kind) => {
+      modbus::RegisterKindStorage::Bcd32(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Bcd64(kind) => {
+      modbus::RegisterKindStorage::Bcd64(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Sm16(kind) => {
+      modbus::RegisterKindStorage::Sm16(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Sm32(kind) => {
+      modbus::RegisterKindStorage::Sm32(to_modbus_numeric_register_kind(kind))
+    }
+    RegisterKindStorage::Sm64(kind) => {
+      modbus::RegisterKindStorage::Sm64(to_modbus_numeric_register_kind(kind))
     }
     RegisterKindStorage::String(StringRegisterKind { length }) => {
       modbus::RegisterKindStorage::String(modbus::StringRegisterKind { length })
@@ -298,9 +904,252 @@ pub(crate) fn to_modbus_register_kind(
     RegisterKindStorage::Raw(RawRegisterKind { length }) => {
       modbus::RegisterKindStorage::Raw(modbus::RawRegisterKind { length })
     }
+    RegisterKindStorage::Bits(BitsRegisterKind { length, bits }) => {
+      let mut bits = bits.into_iter().collect::<Vec<_>>();
+      bits.sort_by_key(|(_, bit)| *bit);
+      modbus::RegisterKindStorage::Bits(modbus::BitsRegisterKind {
+        length: length.unwrap_or(1),
+        bits: std::sync::Arc::new(bits),
+      })
+    }
+    RegisterKindStorage::Enum(EnumRegisterKind { length, labels }) => {
+      let mut labels = labels
+        .into_iter()
+        .map(|(label, code)| (code, label))
+        .collect::<Vec<_>>();
+      labels.sort();
+      modbus::RegisterKindStorage::Enum(modbus::EnumRegisterKind {
+        length: length.unwrap_or(1),
+        labels: std::sync::Arc::new(labels),
+      })
+    }
+    RegisterKindStorage::Datetime(DatetimeRegisterKind {
+      format,
+      timezone,
+      timestamp,
+    }) => modbus::RegisterKindStorage::Datetime(modbus::DatetimeRegisterKind {
+      format: match format {
+        DatetimeFormat::Unix => modbus::DatetimeFormat::Unix,
+        DatetimeFormat::Iec870 => modbus::DatetimeFormat::Iec870,
+        DatetimeFormat::Bcd => modbus::DatetimeFormat::Bcd,
+      },
+      timezone: timezone.unwrap_or(chrono_tz::UTC),
+      timestamp: timestamp.unwrap_or(false),
+    }),
+  }
+}
+
+pub(crate) fn to_modbus_numeric_register_kind(
+  kind: NumericRegisterKind,
+) -> modbus::NumericRegisterKind {
+  modbus::NumericRegisterKind {
+    multiplier: kind.multiplier,
+    offset: kind.offset,
+    endianness: to_modbus_endianness(kind.endianness),
+    sentinels: kind.sentinels,
+    missing: match kind.missing {
+      Some(MissingValuePolicy::Null) => modbus::MissingValuePolicy::Null,
+      Some(MissingValuePolicy::Zero) | None => modbus::MissingValuePolicy::Zero,
+      Some(MissingValuePolicy::Drop) => modbus::MissingValuePolicy::Drop,
+    },
   }
 }
 
+pub(crate) fn to_serial(serial: Serial) -> super::Serial {
+  super::Serial {
+    kinds: serial.kinds.clone(),
+    slaves: serial.slaves.clone().map(to_slaves),
+    miss_tolerance: serial.miss_tolerance,
+    transport: to_modbus_serial_transport(serial),
+  }
+}
+
+fn to_slaves(slaves: Slaves) -> Vec<u8> {
+  match slaves {
+    Slaves::List(slaves) => slaves,
+    Slaves::Range(range) => (range.start..=range.end).collect(),
+  }
+}
+
+fn to_modbus_serial_transport(serial: Serial) -> modbus::SerialTransport {
+  modbus::SerialTransport {
+    path: serial.path,
+    baud_rate: serial.baud_rate.unwrap_or(9600),
+    data_bits: match serial.data_bits {
+      Some(5) => modbus::DataBits::Five,
+      Some(6) => modbus::DataBits::Six,
+      Some(7) => modbus::DataBits::Seven,
+      _ => modbus::DataBits::Eight,
+    },
+    parity: match serial.parity {
+      Some(Parity::Odd) => modbus::Parity::Odd,
+      Some(Parity::Even) => modbus::Parity::Even,
+      Some(Parity::None) | None => modbus::Parity::None,
+    },
+    stop_bits: match serial.stop_bits {
+      Some(2) => modbus::StopBits::Two,
+      _ => modbus::StopBits::One,
+    },
+    delay: milliseconds_to_chrono(serial.delay.unwrap_or(5)),
+  }
+}
+
+pub(crate) fn to_modbus_framing(framing: Framing) -> modbus::Framing {
+  match framing {
+    Framing::Tcp => modbus::Framing::Tcp,
+    Framing::Rtu => modbus::Framing::Rtu,
+  }
+}
+
+pub(crate) fn to_address_range(
+  range: AddressRange,
+) -> Option<(std::net::IpAddr, std::net::IpAddr)> {
+  match (range.start.parse(), range.end.parse()) {
+    (Ok(start), Ok(end)) => Some((start, end)),
+    _ => {
+      tracing::warn!(
+        "Failed parsing address range {:?} - {:?}",
+        range.start,
+        range.end
+      );
+      None
+    }
+  }
+}
+
+// NOTE: gateway addresses are validated when parsing
+pub(crate) fn to_gateway(gateway: Gateway) -> Option<super::Gateway> {
+  Some(super::Gateway {
+    address: gateway.address.parse().ok()?,
+    port: gateway.port,
+    read_timeout: gateway.read_timeout.map(milliseconds_to_chrono),
+    slaves: gateway.slaves.map(to_slaves),
+    delay: gateway.delay.map(milliseconds_to_chrono),
+    miss_tolerance: gateway.miss_tolerance,
+    concurrency: gateway.concurrency,
+    connections: gateway.connections,
+  })
+}
+
+// NOTE: registers are grouped by group name and interval and each group
+// carries the scale factor registers it references
+pub(crate) fn to_measurement_groups(
+  registers: Vec<MeasurementRegister>,
+  groups: HashMap<String, MeasurementGroup>,
+  interval: Option<u32>,
+) -> Vec<super::MeasurementGroup> {
+  let keys = registers
+    .iter()
+    .map(|register| {
+      // NOTE: unknown groups get rejected when the config is parsed
+      let group = register.group.clone();
+      let interval = register
+        .interval
+        .or_else(|| {
+          group
+            .as_ref()
+            .and_then(|name| groups.get(name))
+            .and_then(|group| group.interval)
+        })
+        .or(interval);
+      (register.name.clone(), (group, interval))
+    })
+    .collect::<HashMap<_, _>>();
+  let all = to_modbus_measurement_registers(registers);
+
+  let mut result = Vec::<(
+    (Option<String>, Option<u32>),
+    Vec<modbus::MeasurementRegister<modbus::RegisterKindStorage>>,
+  )>::new();
+  if all.is_empty() {
+    result.push(((None, interval), Vec::new()));
+  }
+  for register in all.iter() {
+    let key = keys
+      .get(&register.name)
+      .cloned()
+      .unwrap_or((None, interval));
+    match result.iter_mut().find(|(other, _)| *other == key) {
+      Some((_, group)) => group.push(register.clone()),
+      None => result.push((key, vec![register.clone()])),
+    }
+  }
+
+  result
+    .into_iter()
+    .map(|((name, interval), mut group)| {
+      let missing = group
+        .iter()
+        .filter_map(|register| register.scale_factor.clone())
+        .filter(|name| !group.iter().any(|register| register.name == *name))
+        .collect::<Vec<_>>();
+      for name in missing {
+        if group.iter().any(|register| register.name == name) {
+          continue;
+        }
+        if let Some(register) =
+          all.iter().find(|register| register.name == name)
+        {
+          group.push(register.clone());
+        }
+      }
+
+      super::MeasurementGroup {
+        name,
+        interval: interval.map(milliseconds_to_chrono),
+        registers: group,
+      }
+    })
+    .collect()
+}
+
+// NOTE: static device locations are validated when parsing
+pub(crate) fn to_static_device(
+  device: StaticDevice,
+) -> Option<super::StaticDevice> {
+  let location = match (device.path, device.address) {
+    (Some(path), _) => super::StaticLocation::Serial(path),
+    (None, address) => super::StaticLocation::Tcp(std::net::SocketAddr::new(
+      address?.parse().ok()?,
+      device.port.unwrap_or(network::DEFAULT_PORT),
+    )),
+  };
+
+  Some(super::StaticDevice {
+    kind: device.kind,
+    location,
+    slave: device.slave,
+    id: device.id,
+  })
+}
+
+// NOTE: the concentrator address is validated when parsing
+pub(crate) fn to_concentrator(
+  concentrator: Concentrator,
+) -> Option<super::Concentrator> {
+  let address = concentrator.address.as_deref().unwrap_or("0.0.0.0");
+  Some(super::Concentrator {
+    address: std::net::SocketAddr::new(
+      address.parse().ok()?,
+      concentrator.port.unwrap_or(network::DEFAULT_PORT),
+    ),
+    max_age: concentrator.max_age.map(milliseconds_to_chrono),
+    units: concentrator
+      .units
+      .into_iter()
+      .map(|unit| super::ConcentratorUnit {
+        unit: unit.unit,
+        device: unit.device,
+        registers: unit
+          .registers
+          .into_iter()
+          .map(|register| (register.name, register.address))
+          .collect(),
+      })
+      .collect(),
+  })
+}
+
 pub(crate) fn make_ip_range(start: String, end: String) -> ipnet::IpAddrRange {
   let (start, end) = match (start.parse(), end.parse()) {
     (Ok(start), Ok(end)) => (start, end),
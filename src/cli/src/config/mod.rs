@@ -2,13 +2,19 @@ mod args;
 mod env;
 mod file;
 
-use std::{collections::HashMap, fs, sync::Arc};
+use std::{
+  collections::HashMap,
+  fs,
+  net::{IpAddr, SocketAddr},
+  sync::Arc,
+};
 
 use ipnet::IpAddrRange;
 use thiserror::Error;
 use tokio::sync::Mutex;
 
 use crate::service::modbus::{self, RegisterValueStorage};
+use crate::service::network;
 
 #[derive(Debug, Clone)]
 pub(crate) struct Db {
@@ -25,6 +31,8 @@ pub(crate) struct Db {
 pub(crate) struct Network {
   pub(crate) timeout: chrono::Duration,
   pub(crate) ip_range: IpAddrRange,
+  pub(crate) ports: Vec<u16>,
+  pub(crate) scan: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -45,27 +53,144 @@ pub(crate) struct Cloud {
 #[derive(Debug, Clone)]
 pub(crate) struct Device {
   pub(crate) kind: String,
+  pub(crate) framing: Option<modbus::Framing>,
+  pub(crate) batching: modbus::Batching,
   pub(crate) id: Vec<modbus::IdRegister<modbus::RegisterKindStorage>>,
+  pub(crate) identification: Option<modbus::IdentificationMatch>,
   pub(crate) detect: Vec<modbus::DetectRegister<modbus::RegisterKindStorage>>,
-  pub(crate) measurement:
-    Vec<modbus::MeasurementRegister<modbus::RegisterKindStorage>>,
+  pub(crate) measurement: Vec<MeasurementGroup>,
   pub(crate) configuration: Vec<modbus::ValueRegister<RegisterValueStorage>>,
   pub(crate) daily: Vec<modbus::ValueRegister<RegisterValueStorage>>,
   pub(crate) nightly: Vec<modbus::ValueRegister<RegisterValueStorage>>,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct MeasurementGroup {
+  pub(crate) name: Option<String>,
+  pub(crate) interval: Option<chrono::Duration>,
+  pub(crate) registers:
+    Vec<modbus::MeasurementRegister<modbus::RegisterKindStorage>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Serial {
+  pub(crate) transport: modbus::SerialTransport,
+  // NOTE: device kinds probed on the port when set instead of all kinds
+  pub(crate) kinds: Option<Vec<String>>,
+  pub(crate) slaves: Option<Vec<u8>>,
+  pub(crate) miss_tolerance: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Gateway {
+  pub(crate) address: IpAddr,
+  pub(crate) port: Option<u16>,
+  pub(crate) read_timeout: Option<chrono::Duration>,
+  pub(crate) slaves: Option<Vec<u8>>,
+  pub(crate) delay: Option<chrono::Duration>,
+  pub(crate) miss_tolerance: Option<u32>,
+  pub(crate) concurrency: Option<usize>,
+  pub(crate) connections: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum StaticLocation {
+  Tcp(SocketAddr),
+  // NOTE: path of a configured serial port
+  Serial(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct StaticDevice {
+  pub(crate) kind: String,
+  pub(crate) location: StaticLocation,
+  pub(crate) slave: Option<u8>,
+  pub(crate) id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Concentrator {
+  pub(crate) address: SocketAddr,
+  // NOTE: values published longer ago are no longer served
+  pub(crate) max_age: Option<chrono::Duration>,
+  pub(crate) units: Vec<ConcentratorUnit>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ConcentratorUnit {
+  pub(crate) unit: u8,
+  pub(crate) device: String,
+  // NOTE: measurement register names and their virtual addresses
+  pub(crate) registers: Vec<(String, u16)>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Modbus {
   pub(crate) read_timeout: chrono::Duration,
   pub(crate) batch_threshold: u16,
+  pub(crate) batch_max_quantity: u16,
+  pub(crate) adaptive_batching: bool,
   pub(crate) termination_timeout: chrono::Duration,
   pub(crate) congestion_backoff: chrono::Duration,
   pub(crate) partial_retries: u32,
+  pub(crate) queue_capacity: usize,
+  pub(crate) request_deadline: chrono::Duration,
   pub(crate) ping_timeout: chrono::Duration,
   pub(crate) tariff_timeout: chrono::Duration,
   pub(crate) inactive_timeout: chrono::Duration,
   pub(crate) discovery_timeout: chrono::Duration,
+  pub(crate) discovery_miss_tolerance: u32,
+  pub(crate) discovery_concurrency: usize,
   pub(crate) devices: HashMap<String, Device>,
+  pub(crate) serial: Vec<Serial>,
+  pub(crate) rtu_over_tcp: Vec<(IpAddr, IpAddr)>,
+  pub(crate) gateways: Vec<Gateway>,
+  pub(crate) static_devices: Vec<StaticDevice>,
+  pub(crate) concentrator: Option<Concentrator>,
+}
+
+impl Modbus {
+  pub(crate) fn framing_for(&self, address: IpAddr) -> modbus::Framing {
+    if self
+      .rtu_over_tcp
+      .iter()
+      .any(|(start, end)| *start <= address && address <= *end)
+    {
+      modbus::Framing::Rtu
+    } else {
+      modbus::Framing::Tcp
+    }
+  }
+
+  pub(crate) fn gateway_for(&self, address: SocketAddr) -> Option<&Gateway> {
+    self
+      .gateways
+      .iter()
+      .find(|gateway| gateway.matches(address))
+  }
+
+  pub(crate) fn serial_for(&self, path: &str) -> Option<&Serial> {
+    self
+      .serial
+      .iter()
+      .find(|serial| serial.transport.path == path)
+  }
+}
+
+impl Serial {
+  pub(crate) fn supports(&self, kind: &str) -> bool {
+    self
+      .kinds
+      .as_ref()
+      .is_none_or(|kinds| kinds.iter().any(|supported| supported == kind))
+  }
+}
+
+impl Gateway {
+  pub(crate) fn matches(&self, address: SocketAddr) -> bool {
+    self.address == address.ip()
+      && self.port.is_none_or(|port| port == address.port())
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -114,12 +239,6 @@ pub(crate) enum ReadError {
   EnvReadError(#[from] env::ParseError),
 }
 
-#[derive(Debug, Error)]
-pub(crate) enum ReloadError {
-  #[error("Failed reading file")]
-  FileReadError(#[from] file::ParseError),
-}
-
 impl Manager {
   pub(crate) async fn new() -> Result<Self, ReadError> {
     let config = Self::read().await?;
@@ -286,12 +405,28 @@ impl Manager {
           config.from_env.network.ip_range_start,
           config.from_env.network.ip_range_end,
         ),
+        ports: config
+          .from_file
+          .network
+          .ports
+          .unwrap_or_else(|| vec![network::DEFAULT_PORT]),
+        scan: config.from_file.network.scan.unwrap_or(true),
       },
       modbus: Modbus {
         read_timeout: file::milliseconds_to_chrono(
           config.from_file.modbus.read_timeout.unwrap_or(100),
         ),
         batch_threshold: config.from_file.modbus.batch_threshold.unwrap_or(4),
+        batch_max_quantity: config
+          .from_file
+          .modbus
+          .batch_max_quantity
+          .unwrap_or(modbus::MAX_BATCH_QUANTITY),
+        adaptive_batching: config
+          .from_file
+          .modbus
+          .adaptive_batching
+          .unwrap_or(false),
         termination_timeout: file::milliseconds_to_chrono(
           config
             .from_file
@@ -303,6 +438,10 @@ impl Manager {
           config.from_file.modbus.termination_timeout.unwrap_or(50),
         ),
         partial_retries: config.from_file.modbus.partial_retries.unwrap_or(10),
+        queue_capacity: config.from_file.modbus.queue_capacity.unwrap_or(1024),
+        request_deadline: file::milliseconds_to_chrono(
+          config.from_file.modbus.request_deadline.unwrap_or(60_000),
+        ),
         ping_timeout: file::milliseconds_to_chrono(
           config.from_file.modbus.ping_timeout.unwrap_or(30_000),
         ),
@@ -319,6 +458,16 @@ impl Manager {
         discovery_timeout: file::milliseconds_to_chrono(
           config.from_file.modbus.discovery_timeout.unwrap_or(5_000),
         ),
+        discovery_miss_tolerance: config
+          .from_file
+          .modbus
+          .discovery_miss_tolerance
+          .unwrap_or(3),
+        discovery_concurrency: config
+          .from_file
+          .modbus
+          .discovery_concurrency
+          .unwrap_or(1),
         devices: config
           .from_file
           .modbus
@@ -329,21 +478,33 @@ impl Manager {
               kind.clone(),
               Device {
                 kind,
+                framing: device.framing.map(file::to_modbus_framing),
+                batching: modbus::Batching {
+                  max_quantity: device.batch_max_quantity,
+                  holes: device
+                    .holes
+                    .into_iter()
+                    .map(file::to_modbus_hole)
+                    .collect(),
+                },
                 id: device
                   .id
                   .into_iter()
                   .map(file::to_modbus_id_register)
                   .collect(),
+                identification: device
+                  .identification
+                  .map(file::to_modbus_identification_match),
                 detect: device
                   .detect
                   .into_iter()
                   .map(file::to_modbus_detect_register)
                   .collect(),
-                measurement: device
-                  .measurement
-                  .into_iter()
-                  .map(file::to_modbus_measurement_register)
-                  .collect(),
+                measurement: file::to_measurement_groups(
+                  device.measurement,
+                  device.groups,
+                  device.interval,
+                ),
                 configuration: device
                   .configuration
                   .into_iter()
@@ -363,6 +524,39 @@ impl Manager {
             )
           })
           .collect::<HashMap<_, _>>(),
+        serial: config
+          .from_file
+          .modbus
+          .serial
+          .into_iter()
+          .map(file::to_serial)
+          .collect(),
+        rtu_over_tcp: config
+          .from_file
+          .modbus
+          .rtu_over_tcp
+          .into_iter()
+          .filter_map(file::to_address_range)
+          .collect(),
+        gateways: config
+          .from_file
+          .modbus
+          .gateways
+          .into_iter()
+          .filter_map(file::to_gateway)
+          .collect(),
+        static_devices: config
+          .from_file
+          .modbus
+          .static_devices
+          .into_iter()
+          .filter_map(file::to_static_device)
+          .collect(),
+        concentrator: config
+          .from_file
+          .modbus
+          .concentrator
+          .and_then(file::to_concentrator),
       },
     }
   }
@@ -57,6 +57,10 @@ async fn main() -> anyhow::Result<()> {
 
   processes.startup().await?;
 
+  if let Err(error) = services.modbus().serve().await {
+    tracing::error!("Failed starting modbus concentrator {}", error);
+  }
+
   if let Err(error) = tokio::signal::ctrl_c().await {
     tracing::error!("Failed waiting for ctrlc signal {}", error);
   };
@@ -68,5 +72,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::error!("Timed out shutting down processes {}", error);
   }
 
+  services.modbus().stop_serving().await;
+
   Ok(())
 }
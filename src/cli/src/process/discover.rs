@@ -1,6 +1,6 @@
 use std::net::SocketAddr;
 
-use futures::future::{join_all, select_all};
+use futures::future::join_all;
 use futures_time::future::FutureExt;
 
 #[allow(unused_imports)]
@@ -31,18 +31,53 @@ impl super::Recurring for Process {
   async fn execute(&self) -> anyhow::Result<()> {
     let config = self.config.values().await;
 
-    let addresses = self.services.network().scan_modbus().await;
+    let addresses = if config.network.scan {
+      self.services.network().scan_modbus().await
+    } else {
+      Vec::new()
+    };
     let addresses_len = addresses.len();
+    let serial_len = config.modbus.serial.len();
 
-    let device_matches = join_all(
-      addresses
+    let transports = addresses
+      .into_iter()
+      .map(|address| Self::transport_for(&config, address))
+      .chain(
+        config
+          .modbus
+          .serial
+          .iter()
+          .map(|serial| modbus::Transport::Serial(serial.transport.clone())),
+      )
+      .collect::<Vec<_>>();
+
+    let probes = join_all(
+      transports
         .into_iter()
-        .map(|address| self.match_address(&config, address)),
+        .map(|transport| self.match_transport(&config, transport)),
     )
     .await
     .into_iter()
     .flatten()
     .collect::<Vec<_>>();
+    self.record_probes(&probes).await;
+
+    let device_matches = probes
+      .into_iter()
+      .filter_map(|probe| probe.result.ok())
+      .chain(
+        join_all(
+          config
+            .modbus
+            .static_devices
+            .iter()
+            .map(|device| self.match_static(&config, device)),
+        )
+        .await
+        .into_iter()
+        .flatten(),
+      )
+      .collect::<Vec<_>>();
     let device_matches_len = device_matches.len();
 
     let consolidated_matches = join_all(
@@ -54,8 +89,9 @@ impl super::Recurring for Process {
     let consolidated_matches_len = consolidated_matches.len();
 
     tracing::info!(
-      "Scanned {:?} modbus servers with {:?} devices of which {:?} were consolidated",
+      "Scanned {:?} modbus servers and {:?} serial ports with {:?} devices of which {:?} were consolidated",
       addresses_len,
+      serial_len,
       device_matches_len,
       consolidated_matches_len
     );
@@ -69,34 +105,139 @@ struct DeviceMatch {
   id: String,
   kind: String,
   destination: modbus::Destination,
+  batching: modbus::Batching,
+  identification: Option<modbus::Identification>,
+  // NOTE: static devices with an expected id match without responding
+  reachable: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Rejection {
+  Unsupported,
+  Undetected,
+  Unidentified,
+}
+
+#[derive(Debug, Clone)]
+struct Probe {
+  destination: modbus::Destination,
+  timestamp: chrono::DateTime<chrono::Utc>,
+  result: Result<DeviceMatch, Rejection>,
 }
 
 impl Process {
   #[tracing::instrument(skip(self, config))]
-  async fn match_address(
+  async fn match_transport(
     &self,
     config: &config::Values,
-    address: SocketAddr,
-  ) -> Vec<DeviceMatch> {
-    if let Some(device_match) = self
-      .match_destination(config, modbus::Destination::standalone_for(address))
-      .await
-    {
-      return vec![device_match];
+    transport: modbus::Transport,
+  ) -> Vec<Probe> {
+    let mut probes = Vec::new();
+    if transport.supports_standalone() {
+      let probe = self
+        .probe(
+          config,
+          modbus::Destination::standalone_for(transport.clone()),
+        )
+        .await;
+      let matched = probe.result.is_ok();
+      probes.push(probe);
+      if matched {
+        return probes;
+      }
     }
 
-    let mut device_matches = Vec::new();
-    for destination in modbus::Destination::slaves_for(address) {
-      if let Some(device_match) =
-        self.match_destination(config, destination).await
-      {
-        device_matches.push(device_match);
-      } else {
+    let (gateway, serial) = match &transport {
+      modbus::Transport::Tcp(address)
+      | modbus::Transport::RtuOverTcp(address) => {
+        (config.modbus.gateway_for(*address), None)
+      }
+      modbus::Transport::Serial(serial) => {
+        (None, config.modbus.serial_for(&serial.path))
+      }
+    };
+    let miss_tolerance = gateway
+      .and_then(|gateway| gateway.miss_tolerance)
+      .or_else(|| serial.and_then(|serial| serial.miss_tolerance))
+      .unwrap_or(config.modbus.discovery_miss_tolerance);
+    let concurrency = gateway
+      .and_then(|gateway| gateway.concurrency)
+      .unwrap_or(config.modbus.discovery_concurrency)
+      .max(1);
+    let slaves = gateway
+      .and_then(|gateway| gateway.slaves.clone())
+      .or_else(|| serial.and_then(|serial| serial.slaves.clone()));
+    let destinations =
+      modbus::Destination::slaves_for(transport, slaves).collect::<Vec<_>>();
+
+    // NOTE: misses are counted across chunks so only consecutive misses stop the scan
+    let mut misses = 0u32;
+    for chunk in destinations.chunks(concurrency) {
+      let chunk_probes = join_all(
+        chunk
+          .iter()
+          .cloned()
+          .map(|destination| self.probe(config, destination)),
+      )
+      .await;
+      for probe in chunk_probes {
+        misses = if probe.result.is_ok() {
+          0
+        } else {
+          misses.saturating_add(1)
+        };
+        probes.push(probe);
+      }
+
+      if misses > miss_tolerance {
         break;
       }
     }
 
-    device_matches
+    probes
+  }
+
+  async fn probe(
+    &self,
+    config: &config::Values,
+    destination: modbus::Destination,
+  ) -> Probe {
+    let result = self.match_destination(config, destination.clone()).await;
+    if let Err(rejection) = &result {
+      tracing::trace!("Rejected {:?} as {:?}", destination, rejection);
+    }
+
+    Probe {
+      destination,
+      timestamp: chrono::Utc::now(),
+      result,
+    }
+  }
+
+  #[tracing::instrument(skip_all)]
+  async fn record_probes(&self, probes: &[Probe]) {
+    let db_probes = probes
+      .iter()
+      .map(|probe| db::Probe {
+        timestamp: probe.timestamp,
+        destination: db::to_db_destination(&probe.destination),
+        result: match &probe.result {
+          Ok(_) => db::ProbeResult::Matched,
+          Err(Rejection::Unsupported) => db::ProbeResult::Unsupported,
+          Err(Rejection::Undetected) => db::ProbeResult::Undetected,
+          Err(Rejection::Unidentified) => db::ProbeResult::Unidentified,
+        },
+        device: probe
+          .result
+          .as_ref()
+          .ok()
+          .map(|device_match| device_match.id.clone()),
+      })
+      .collect::<Vec<_>>();
+
+    if let Err(error) = self.services.db().replace_probes(db_probes).await {
+      tracing::error!("Failed recording probes {}", error);
+    }
   }
 
   #[tracing::instrument(skip(self, config))]
@@ -104,25 +245,66 @@ impl Process {
     &self,
     config: &config::Values,
     destination: modbus::Destination,
-  ) -> Option<DeviceMatch> {
-    let device = select_all(config.modbus.devices.values().map(|device| {
-      Box::pin(
-        self
-          .match_device(device.clone(), destination)
-          .timeout(timeout_from_chrono(config.modbus.discovery_timeout)),
-      )
+  ) -> Result<DeviceMatch, Rejection> {
+    let devices = config
+      .modbus
+      .devices
+      .values()
+      .filter(|device| {
+        Self::supports_transport(config, device, &destination.transport)
+      })
+      .collect::<Vec<_>>();
+    if devices.is_empty() {
+      return Err(Rejection::Unsupported);
+    }
+
+    // NOTE: identification is read once and shared by all candidate devices
+    let identification = if devices
+      .iter()
+      .any(|device| device.identification.is_some())
+    {
+      self
+        .services
+        .modbus()
+        .identify_destination(destination.clone())
+        .timeout(timeout_from_chrono(config.modbus.discovery_timeout))
+        .await
+        .ok()
+        .and_then(|identification| {
+          identification
+            .inspect_err(|error| {
+              tracing::trace!("Failed identifying {:?} {}", destination, error)
+            })
+            .ok()
+        })
+    } else {
+      None
+    };
+
+    // NOTE: a kind that rules itself out right away must not win over a kind
+    // that matches later so every kind gets its answer
+    let device = join_all(devices.into_iter().map(|device| {
+      self
+        .match_device(
+          device.clone(),
+          destination.clone(),
+          identification.as_ref(),
+        )
+        .timeout(timeout_from_chrono(config.modbus.discovery_timeout))
     }))
     .await
-    .0
-    .ok()
-    .flatten()?;
+    .into_iter()
+    .find_map(|device| device.ok().flatten())
+    .ok_or(Rejection::Undetected)?;
 
-    let device_match = self
-      .match_id(device, destination)
+    let mut device_match = self
+      .match_id(device, destination.clone())
       .timeout(timeout_from_chrono(config.modbus.discovery_timeout))
       .await
       .ok()
-      .flatten()?;
+      .flatten()
+      .ok_or(Rejection::Unidentified)?;
+    device_match.identification = identification;
 
     tracing::debug!(
       "Matched {:?} devices on {:?}",
@@ -130,7 +312,117 @@ impl Process {
       device_match.destination
     );
 
-    Some(device_match)
+    Ok(device_match)
+  }
+
+  #[tracing::instrument(skip(self, config))]
+  async fn match_static(
+    &self,
+    config: &config::Values,
+    static_device: &config::StaticDevice,
+  ) -> Option<DeviceMatch> {
+    let device = match config.modbus.devices.get(&static_device.kind) {
+      Some(device) => device.clone(),
+      None => {
+        tracing::warn!("No config for static device {:?}", static_device);
+        return None;
+      }
+    };
+    let transport = match &static_device.location {
+      config::StaticLocation::Tcp(address) => {
+        let framing = device
+          .framing
+          .unwrap_or_else(|| config.modbus.framing_for(address.ip()));
+        modbus::Transport::tcp(*address, framing)
+      }
+      config::StaticLocation::Serial(path) => {
+        match config.modbus.serial_for(path) {
+          Some(serial) => modbus::Transport::Serial(serial.transport.clone()),
+          None => {
+            tracing::warn!("No serial port for static device {:?}", path);
+            return None;
+          }
+        }
+      }
+    };
+    let destination = modbus::Destination {
+      transport,
+      slave: static_device.slave,
+    };
+
+    let device_match = self
+      .match_id(device.clone(), destination.clone())
+      .timeout(timeout_from_chrono(config.modbus.discovery_timeout))
+      .await
+      .ok()
+      .flatten();
+
+    // NOTE: an expected id lets unreachable devices get pinged until they respond
+    match (device_match, static_device.id.clone()) {
+      (Some(device_match), Some(id)) if device_match.id != id => {
+        tracing::warn!(
+          "Static device id mismatch {:?} != {:?}",
+          device_match.id,
+          id
+        );
+        None
+      }
+      (Some(device_match), _) => Some(device_match),
+      (None, Some(id)) => Some(DeviceMatch {
+        id,
+        kind: device.kind,
+        destination,
+        batching: device.batching,
+        identification: None,
+        reachable: false,
+      }),
+      (None, None) => {
+        tracing::warn!("Failed reading static device {:?} id", static_device);
+        None
+      }
+    }
+  }
+
+  // NOTE: only addresses configured for rtu framing get probed with rtu so
+  // plain modbus tcp servers never see rtu frames
+  fn transport_for(
+    config: &config::Values,
+    address: SocketAddr,
+  ) -> modbus::Transport {
+    modbus::Transport::tcp(address, config.modbus.framing_for(address.ip()))
+  }
+
+  fn supports_transport(
+    config: &config::Values,
+    device: &config::Device,
+    transport: &modbus::Transport,
+  ) -> bool {
+    if let modbus::Transport::Serial(serial) = transport {
+      if !config
+        .modbus
+        .serial_for(&serial.path)
+        .is_none_or(|serial| serial.supports(&device.kind))
+      {
+        return false;
+      }
+    }
+
+    // NOTE: only tcp framing can identify so kinds matched by identification
+    // alone can't be detected anywhere else
+    if device.detect.is_empty()
+      && device.identification.is_some()
+      && transport.framing() != modbus::Framing::Tcp
+    {
+      return false;
+    }
+
+    match (device.framing, transport) {
+      (Some(framing), transport) => framing == transport.framing(),
+      (None, modbus::Transport::RtuOverTcp(address)) => {
+        config.modbus.framing_for(address.ip()) == modbus::Framing::Rtu
+      }
+      (None, _) => true,
+    }
   }
 
   #[tracing::instrument(skip(self))]
@@ -149,16 +441,20 @@ impl Process {
 
         return None;
       }
-      Ok(Some(_)) => {
+      Ok(Some(device)) => {
         let now = chrono::Utc::now();
+        let seen = if device_match.reachable {
+          now
+        } else {
+          device.seen
+        };
         if let Err(error) = self
           .services
           .db()
           .update_device_destination(
             &device_match.id,
-            db::to_db_address(device_match.destination.address.ip()),
-            db::to_db_slave(device_match.destination.slave),
-            now,
+            db::to_db_destination(&device_match.destination),
+            seen,
             now,
           )
           .await
@@ -167,20 +463,46 @@ impl Process {
 
           return None;
         }
+
+        if let Some(identification) = device_match
+          .identification
+          .as_ref()
+          .and_then(|identification| serde_json::to_value(identification).ok())
+        {
+          if let Err(error) = self
+            .services
+            .db()
+            .update_device_identification(&device_match.id, identification)
+            .await
+          {
+            tracing::error!("Failed updating device identification {}", error);
+          }
+        }
       }
       Ok(None) => {
         let now = chrono::Utc::now();
+        let destination = db::to_db_destination(&device_match.destination);
         if let Err(error) = self
           .services
           .db()
           .insert_device(db::Device {
             id: device_match.id.clone(),
             kind: device_match.kind.clone(),
-            status: db::DeviceStatus::Healthy,
+            status: if device_match.reachable {
+              db::DeviceStatus::Healthy
+            } else {
+              db::DeviceStatus::Unreachable
+            },
             seen: now,
             pinged: now,
-            address: db::to_db_address(device_match.destination.address.ip()),
-            slave: db::to_db_slave(device_match.destination.slave),
+            address: destination.address,
+            port: destination.port,
+            path: destination.path,
+            framing: destination.framing,
+            slave: destination.slave,
+            identification: device_match.identification.as_ref().and_then(
+              |identification| serde_json::to_value(identification).ok(),
+            ),
           })
           .await
         {
@@ -194,7 +516,11 @@ impl Process {
     self
       .services
       .modbus()
-      .bind(device_match.id.clone(), device_match.destination)
+      .bind(
+        device_match.id.clone(),
+        device_match.destination.clone(),
+        device_match.batching.clone(),
+      )
       .await;
 
     tracing::debug!("Matched device");
@@ -206,11 +532,25 @@ impl Process {
     &self,
     device: config::Device,
     destination: modbus::Destination,
+    identification: Option<&modbus::Identification>,
   ) -> Option<config::Device> {
+    // NOTE: fall back to detect registers when the device can't identify itself
+    match (&device.identification, identification) {
+      (Some(r#match), Some(identification)) => {
+        return r#match.matches(identification).then_some(device);
+      }
+      (Some(_), None) if device.detect.is_empty() => return None,
+      _ => {}
+    }
+
     let registers = self
       .services
       .modbus()
-      .read_from_destination(destination, device.detect.clone())
+      .read_from_destination(
+        destination,
+        &device.batching,
+        device.detect.clone(),
+      )
       .await
       .ok()?;
 
@@ -233,13 +573,16 @@ impl Process {
     let registers = self
       .services
       .modbus()
-      .read_from_destination(destination, device.id)
+      .read_from_destination(destination.clone(), &device.batching, device.id)
       .await;
 
     registers.ok().map(|id_registers| DeviceMatch {
       kind: device.kind.clone(),
       destination,
+      batching: device.batching,
       id: modbus::make_id(device.kind, id_registers),
+      identification: None,
+      reachable: true,
     })
   }
 }
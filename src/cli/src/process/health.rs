@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 #[allow(unused_imports)]
 use crate::{service::*, *};
 
@@ -7,6 +9,9 @@ pub(crate) struct Process {
 
   #[allow(unused)]
   services: service::Container,
+
+  // NOTE: metrics persisted before a restart get seeded on the first run
+  restored: AtomicBool,
 }
 
 impl Process {
@@ -14,7 +19,11 @@ impl Process {
     config: config::Manager,
     services: service::Container,
   ) -> Self {
-    Self { config, services }
+    Self {
+      config,
+      services,
+      restored: AtomicBool::new(false),
+    }
   }
 }
 
@@ -24,11 +33,52 @@ impl super::Process for Process {}
 impl process::Recurring for Process {
   async fn execute(&self) -> anyhow::Result<()> {
     let temperature = self.services.hardware().read_temperature().await?;
+    if !self.restored.load(Ordering::Relaxed) {
+      match self.services.db().get_gateway_metrics().await {
+        Ok(persisted) => {
+          self
+            .services
+            .modbus()
+            .seed_metrics(
+              persisted.into_iter().filter_map(db::to_gateway_metrics),
+            )
+            .await;
+          self.restored.store(true, Ordering::Relaxed);
+        }
+        Err(error) => {
+          tracing::error!("Failed fetching gateway metrics {}", error);
+        }
+      }
+    }
+    let modbus = self.services.modbus().metrics().await;
+    // NOTE: persisting before restoring would overwrite the persisted metrics
+    if self.restored.load(Ordering::Relaxed) {
+      let now = chrono::Utc::now();
+      if let Err(error) = self
+        .services
+        .db()
+        .upsert_gateway_metrics(
+          modbus
+            .iter()
+            .filter_map(|metrics| db::to_db_gateway_metrics(metrics, now))
+            .collect(),
+        )
+        .await
+      {
+        tracing::error!("Failed persisting gateway metrics {}", error);
+      }
+    }
 
     let result = self
       .services
       .cloud()
-      .update(serde_json::json!(Health { temperature }), vec![])
+      .update(
+        serde_json::json!(Health {
+          temperature,
+          modbus
+        }),
+        vec![],
+      )
       .await;
 
     let (log_status, log_response) = match result {
@@ -67,7 +117,8 @@ impl process::Recurring for Process {
   }
 }
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Serialize)]
 struct Health {
   temperature: f32,
+  modbus: Vec<modbus::GatewayMetrics>,
 }
@@ -76,6 +76,14 @@ struct Device {
   id_registers: Vec<modbus::IdRegister<modbus::RegisterKindStorage>>,
   measurement_registers:
     Vec<modbus::MeasurementRegister<modbus::RegisterKindStorage>>,
+  group: Option<String>,
+  interval: Option<chrono::Duration>,
+}
+
+impl Device {
+  fn key(&self) -> (&str, Option<&str>, Option<chrono::Duration>) {
+    (self.id.as_str(), self.group.as_deref(), self.interval)
+  }
 }
 
 struct DeviceStream {
@@ -117,6 +125,19 @@ impl Process {
           tracing::warn!("Device server failed {:?} {}", device.id, error);
           return false;
         }
+        Some(Some(Err(modbus::ServerReadError::Exception(exception)))) => {
+          tracing::warn!(
+            "Device {:?} responded with exception {:?}",
+            device.id,
+            exception
+          );
+        }
+        Some(Some(Err(
+          error @ (modbus::ServerReadError::Overloaded
+          | modbus::ServerReadError::DeadlineExceeded),
+        ))) => {
+          tracing::warn!("Device {:?} read rejected {}", device.id, error);
+        }
         Some(Some(Err(modbus::ServerReadError::ParsingFailed(error)))) => {
           tracing::warn!("Parsing failed {:?} {}", device.id, error);
         }
@@ -155,7 +176,8 @@ impl Process {
     };
     let db_devices_len = db_devices.len();
 
-    let merged_devices = db_devices
+    // NOTE: each measurement group of a device gets its own stream
+    let mut merged_devices = db_devices
       .into_iter()
       .filter_map(|device| {
         config
@@ -163,14 +185,24 @@ impl Process {
           .devices
           .values()
           .find(|device_config| device_config.kind == device.kind)
-          .map(|config| Device {
-            id: device.id,
-            kind: device.kind,
-            id_registers: config.id.clone(),
-            measurement_registers: config.measurement.clone(),
+          .map(|config| {
+            config
+              .measurement
+              .iter()
+              .map(|group| Device {
+                id: device.id.clone(),
+                kind: device.kind.clone(),
+                id_registers: config.id.clone(),
+                measurement_registers: group.registers.clone(),
+                group: group.name.clone(),
+                interval: group.interval,
+              })
+              .collect::<Vec<_>>()
           })
       })
+      .flatten()
       .collect::<Vec<_>>();
+    merged_devices.sort_by(|x, y| Ord::cmp(&x.key(), &y.key()));
     let merged_devices_len = merged_devices.len();
 
     tracing::debug!(
@@ -194,7 +226,7 @@ impl Process {
     let merged_devices = devices
       .drain(0..)
       .merge_join_by(new_devices.into_iter(), |x, y| {
-        Ord::cmp(x.device.id.as_str(), y.id.as_str())
+        Ord::cmp(&x.device.key(), &y.key())
       })
       .filter_map(|x| match x {
         itertools::EitherOrBoth::Both(old_device, new_device) => {
@@ -248,6 +280,7 @@ impl Process {
   async fn consolidate(&self, measurements: Vec<DeviceRegisters>) {
     let measurements_len = measurements.len();
 
+    let mut published = Vec::new();
     let verified_measurements = measurements
       .into_iter()
       .filter_map(|measurement| {
@@ -283,21 +316,38 @@ impl Process {
           }
           .storage
           .timestamp();
-
-        let data = modbus::serialize_registers(
-          measurement.registers.into_iter().filter_map(Either::right),
-        );
+        let timestamp = measurement
+          .registers
+          .iter()
+          .filter_map(|register| register.as_ref().right())
+          .find_map(|register| register.storage.designated_timestamp())
+          .unwrap_or(timestamp);
+
+        let registers = measurement
+          .registers
+          .into_iter()
+          .filter_map(Either::right)
+          .collect::<Vec<_>>();
+        let registers = modbus::apply_scale_factors(registers);
+        published.push((source.clone(), registers.clone()));
+        let data = modbus::serialize_registers(registers);
 
         Some(db::Measurement {
           id: 0,
           source,
           timestamp,
           data,
+          group_name: measurement.device.group,
         })
       })
       .collect::<Vec<_>>();
     let verified_measurements_len = verified_measurements.len();
 
+    // NOTE: only verified measurements reach the concentrator
+    for (source, registers) in published {
+      self.services.modbus().publish(&source, &registers).await;
+    }
+
     if let Err(error) = self
       .services
       .db()
@@ -334,6 +384,7 @@ impl Process {
             .map(Either::Right)
             .chain(device.id_registers.into_iter().map(Either::Left))
             .collect::<Vec<_>>(),
+          device.interval,
         )
         .await?,
     ))
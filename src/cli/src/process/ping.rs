@@ -41,9 +41,10 @@ impl process::Recurring for Process {
     )
     .await;
     let pinged_devices_len = pinged_devices.len();
-    let healthy_count = pinged_devices.iter().filter(|pinged| **pinged).count();
+    let healthy_count =
+      pinged_devices.iter().filter(|ping| ping.pinged).count();
     let unreachable_count =
-      pinged_devices.iter().filter(|pinged| !**pinged).count();
+      pinged_devices.iter().filter(|ping| !ping.pinged).count();
     tracing::info!(
       "Pinged {:?} devices of which {:?} are healthy and {:?} unreachable",
       pinged_devices_len,
@@ -55,7 +56,7 @@ impl process::Recurring for Process {
       pinged_devices
         .into_iter()
         .zip(devices)
-        .map(|(pinged, device)| self.consolidate(&config, device, pinged)),
+        .map(|(ping, device)| self.consolidate(&config, device, ping)),
     )
     .await;
     let consolidated_devices_len = consolidated_devices.len();
@@ -95,13 +96,26 @@ impl process::Recurring for Process {
   }
 }
 
+#[derive(Clone, Debug)]
+struct Ping {
+  pinged: bool,
+  exception: Option<modbus::Exception>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthException {
+  exception: modbus::Exception,
+  class: modbus::ExceptionClass,
+}
+
 impl Process {
   #[tracing::instrument(skip(self, config))]
   async fn ping_device(
     &self,
     config: &config::Values,
     device: db::Device,
-  ) -> bool {
+  ) -> Ping {
     match config
       .modbus
       .devices
@@ -118,29 +132,53 @@ impl Process {
         {
           Err(error) => {
             tracing::warn!("Getting id timed out {}", error);
-            return false;
+            return Ping {
+              pinged: false,
+              exception: None,
+            };
+          }
+          Ok(Err(modbus::DeviceReadError::ServerRead(
+            modbus::ServerReadError::Exception(exception),
+          ))) => {
+            tracing::warn!("Getting id failed with exception {:?}", exception);
+            return Ping {
+              pinged: false,
+              exception: Some(exception),
+            };
           }
           Ok(Err(error)) => {
             tracing::warn!("Getting id failed {}", error);
-            return false;
+            return Ping {
+              pinged: false,
+              exception: None,
+            };
           }
           Ok(Ok(id_registers)) => {
             if modbus::make_id(device.kind, id_registers) == device.id {
               tracing::debug!("Id match");
             } else {
               tracing::debug!("Id mismatch");
-              return false;
+              return Ping {
+                pinged: false,
+                exception: None,
+              };
             }
           }
         }
       }
       None => {
         tracing::debug!("Config not found");
-        return false;
+        return Ping {
+          pinged: false,
+          exception: None,
+        };
       }
     }
 
-    true
+    Ping {
+      pinged: true,
+      exception: None,
+    }
   }
 
   #[tracing::instrument(skip(self, config, device), fields(id = ?device.id))]
@@ -148,8 +186,9 @@ impl Process {
     &self,
     config: &config::Values,
     device: db::Device,
-    pinged: bool,
+    ping: Ping,
   ) -> anyhow::Result<(db::Device, db::DeviceStatus)> {
+    let pinged = ping.pinged;
     let now = chrono::Utc::now();
     let status = if pinged {
       db::DeviceStatus::Healthy
@@ -178,29 +217,61 @@ impl Process {
     if remove {
       self.services.modbus().stop_from_id(&device.id).await;
     } else {
-      self
-        .services
-        .modbus()
-        .bind(
-          device.id.clone(),
-          modbus::Destination {
-            address: network::to_socket(db::to_address(device.address)),
-            slave: db::to_slave(device.slave),
-          },
-        )
-        .await;
+      match db::to_transport(
+        device.address,
+        device.port,
+        device.path.clone(),
+        device.framing,
+        config.modbus.serial.iter().map(|serial| &serial.transport),
+      ) {
+        Some(transport) => {
+          self
+            .services
+            .modbus()
+            .bind(
+              device.id.clone(),
+              modbus::Destination {
+                transport,
+                slave: db::to_slave(device.slave),
+              },
+              config
+                .modbus
+                .devices
+                .values()
+                .find(|device_config| device_config.kind == device.kind)
+                .map(|device_config| device_config.batching.clone())
+                .unwrap_or_default(),
+            )
+            .await;
+          if config.modbus.adaptive_batching {
+            self.consolidate_batch_plan(&device.id).await;
+          }
+          self.consolidate_batch_splits(&device.id).await;
+        }
+        None => {
+          tracing::warn!("No transport configured for device {}", device.id);
+        }
+      }
     }
 
-    if update {
+    // NOTE: exceptions are pushed every ping so the cloud sees their class
+    if update || ping.exception.is_some() {
+      let data = match ping.exception {
+        Some(exception) => serde_json::json!(HealthException {
+          exception,
+          class: exception.class(),
+        }),
+        None => serde_json::Value::Object(serde_json::Map::new()),
+      };
       if let Err(error) = self
         .services
         .db()
         .insert_health(db::Health {
           id: 0,
           source: device.id.clone(),
-          timestamp: seen,
+          timestamp: if update { seen } else { now },
           status,
-          data: serde_json::Value::Object(serde_json::Map::new()),
+          data,
         })
         .await
       {
@@ -212,6 +283,72 @@ impl Process {
 
     Ok((device, status))
   }
+
+  // NOTE: the stored plan only gets written when the worker adapted it
+  #[tracing::instrument(skip(self))]
+  async fn consolidate_batch_plan(&self, id: &str) {
+    let stored = match self.services.db().get_batch_plan(id).await {
+      Ok(db_plan) => db_plan.as_ref().map(db::to_batch_plan),
+      Err(error) => {
+        tracing::error!("Failed fetching batch plan {}", error);
+        return;
+      }
+    };
+    if let Some(stored) = stored {
+      self.services.modbus().seed_plan_from_id(id, stored).await;
+    }
+
+    let plan = match self.services.modbus().plan_from_id(id).await {
+      Some(plan) if Some(plan) != stored => plan,
+      _ => return,
+    };
+    if let Err(error) = self
+      .services
+      .db()
+      .upsert_batch_plan(db::to_db_batch_plan(
+        id.to_string(),
+        plan,
+        chrono::Utc::now(),
+      ))
+      .await
+    {
+      tracing::error!("Failed updating batch plan {}", error);
+    }
+  }
+
+  // NOTE: splits learned before a restart are seeded back and new ones get
+  // added so devices don't have to fail their way to them again
+  #[tracing::instrument(skip(self))]
+  async fn consolidate_batch_splits(&self, id: &str) {
+    let stored = match self.services.db().get_batch_splits(id).await {
+      Ok(db_splits) => {
+        db_splits.iter().map(db::to_batch_split).collect::<Vec<_>>()
+      }
+      Err(error) => {
+        tracing::error!("Failed fetching batch splits {}", error);
+        return;
+      }
+    };
+    self
+      .services
+      .modbus()
+      .seed_splits_from_id(id, stored.iter().copied())
+      .await;
+
+    let now = chrono::Utc::now();
+    let learned = self
+      .services
+      .modbus()
+      .splits_from_id(id)
+      .await
+      .into_iter()
+      .filter(|split| !stored.contains(split))
+      .map(|split| db::to_db_batch_split(id.to_string(), split, now))
+      .collect::<Vec<_>>();
+    if let Err(error) = self.services.db().insert_batch_splits(learned).await {
+      tracing::error!("Failed inserting batch splits {}", error);
+    }
+  }
 }
 
 fn timeout_from_chrono(
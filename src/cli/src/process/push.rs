@@ -119,6 +119,7 @@ impl Process {
             meter_id: measurement.source,
             timestamp: measurement.timestamp,
             data: serde_json::json!(measurement.data),
+            group: measurement.group_name,
           })
           .collect(),
       )
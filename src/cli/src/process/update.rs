@@ -20,11 +20,6 @@ impl Process {
 
 impl super::Process for Process {}
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-struct PidgeonHealth {
-  temperature: f32,
-}
-
 #[async_trait::async_trait]
 impl process::Recurring for Process {
   async fn execute(&self) -> anyhow::Result<()> {
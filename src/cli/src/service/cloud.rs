@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use reqwest::{
-  header::{HeaderMap, HeaderValue, InvalidHeaderValue},
+  header::{HeaderMap, HeaderValue},
   Client as HttpClient, Error as HttpError,
 };
 use serde::{Deserialize, Serialize};
@@ -18,6 +18,8 @@ pub(crate) struct Measurement {
   pub(crate) meter_id: String,
   pub(crate) timestamp: DateTime<Utc>,
   pub(crate) data: serde_json::Value,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) group: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,18 +45,6 @@ pub(crate) struct Service {
   http: HttpClient,
 }
 
-#[derive(Debug, Error)]
-pub(crate) enum ConstructionError {
-  #[error("HTTP client construction error")]
-  HttpError(#[from] HttpError),
-
-  #[error("Invalid header error")]
-  InvalidHeader(#[from] InvalidHeaderValue),
-
-  #[error("IO error")]
-  IO(#[from] std::io::Error),
-}
-
 #[derive(Debug, Error)]
 pub(crate) enum RequestError {
   #[error("HTTP Post error")]
@@ -7,7 +7,7 @@ use sqlx::{
 };
 use thiserror::Error;
 
-use crate::*;
+use crate::{service::*, *};
 
 // TODO: check if lists are empty before sending requests
 
@@ -24,15 +24,35 @@ pub(crate) enum DeviceStatus {
   Inactive,
 }
 
+#[derive(Debug, Copy, Clone, Type, Eq, PartialEq)]
+#[sqlx(type_name = "device_framing", rename_all = "lowercase")]
+pub(crate) enum DeviceFraming {
+  Tcp,
+  Rtu,
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub(crate) struct Device {
   pub(crate) id: String,
   pub(crate) kind: String,
   pub(crate) status: DeviceStatus,
-  pub(crate) address: IpNetwork,
+  pub(crate) address: Option<IpNetwork>,
+  pub(crate) port: Option<i32>,
+  pub(crate) path: Option<String>,
+  pub(crate) framing: DeviceFraming,
   pub(crate) seen: DateTime<Utc>,
   pub(crate) pinged: DateTime<Utc>,
   pub(crate) slave: Option<i32>,
+  pub(crate) identification: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DeviceDestination {
+  pub(crate) address: Option<IpNetwork>,
+  pub(crate) port: Option<i32>,
+  pub(crate) path: Option<String>,
+  pub(crate) framing: DeviceFraming,
+  pub(crate) slave: Option<i32>,
 }
 
 #[derive(Debug, Clone, FromRow)]
@@ -42,6 +62,7 @@ pub(crate) struct Measurement {
   pub(crate) source: String,
   pub(crate) timestamp: DateTime<Utc>,
   pub(crate) data: serde_json::Value,
+  pub(crate) group_name: Option<String>,
 }
 
 #[derive(Debug, Clone, FromRow)]
@@ -54,6 +75,56 @@ pub(crate) struct Health {
   pub(crate) data: serde_json::Value,
 }
 
+#[derive(Debug, Copy, Clone, Type, Eq, PartialEq)]
+#[sqlx(type_name = "probe_result", rename_all = "lowercase")]
+pub(crate) enum ProbeResult {
+  Matched,
+  Unsupported,
+  Undetected,
+  Unidentified,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Probe {
+  pub(crate) timestamp: DateTime<Utc>,
+  pub(crate) destination: DeviceDestination,
+  pub(crate) result: ProbeResult,
+  pub(crate) device: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct BatchPlan {
+  pub(crate) device_id: String,
+  pub(crate) threshold: i32,
+  pub(crate) max_quantity: i32,
+  pub(crate) updated: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct GatewayMetrics {
+  pub(crate) gateway: String,
+  pub(crate) metrics: serde_json::Value,
+  pub(crate) updated: DateTime<Utc>,
+}
+
+#[derive(Debug, Copy, Clone, Type, Eq, PartialEq)]
+#[sqlx(type_name = "register_table", rename_all = "lowercase")]
+pub(crate) enum RegisterTable {
+  Holding,
+  Input,
+  Coil,
+  Discrete,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub(crate) struct BatchSplit {
+  pub(crate) device_id: String,
+  pub(crate) register_table: RegisterTable,
+  pub(crate) address: i32,
+  pub(crate) quantity: i32,
+  pub(crate) updated: DateTime<Utc>,
+}
+
 #[derive(Debug, Copy, Clone, Type, Eq, PartialEq)]
 #[sqlx(type_name = "log_status", rename_all = "lowercase")]
 pub(crate) enum LogStatus {
@@ -137,7 +208,7 @@ impl Service {
     let devices = sqlx::query_as!(
       Device,
       r#"
-        select id, kind, status as "status: DeviceStatus", seen, pinged, address, slave
+        select id, kind, status as "status: DeviceStatus", seen, pinged, address, port, path, framing as "framing: DeviceFraming", slave, identification
         from devices
       "#,
     )
@@ -158,7 +229,7 @@ impl Service {
     let device = sqlx::query_as!(
       Device,
       r#"
-        select id, kind, status as "status: DeviceStatus", seen, pinged, address, slave
+        select id, kind, status as "status: DeviceStatus", seen, pinged, address, port, path, framing as "framing: DeviceFraming", slave, identification
         from devices
         where id = $1
       "#,
@@ -180,8 +251,8 @@ impl Service {
     #[allow(clippy::panic)] // NOTE: sqlx thing
     sqlx::query!(
       r#"
-        insert into devices (id, kind, status, seen, pinged, address, slave)
-        values ($1, $2, $3, $4, $5, $6, $7)
+        insert into devices (id, kind, status, seen, pinged, address, port, path, framing, slave, identification)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
       "#,
       device.id,
       device.kind,
@@ -189,7 +260,11 @@ impl Service {
       device.seen,
       device.pinged,
       device.address,
-      device.slave
+      device.port,
+      device.path,
+      device.framing as DeviceFraming,
+      device.slave,
+      device.identification
     )
     .execute(&self.pool)
     .await?;
@@ -249,8 +324,7 @@ impl Service {
   pub(crate) async fn update_device_destination(
     &self,
     id: &str,
-    address: IpNetwork,
-    slave: Option<i32>,
+    destination: DeviceDestination,
     seen: DateTime<Utc>,
     pinged: DateTime<Utc>,
   ) -> Result<(), Error> {
@@ -258,12 +332,15 @@ impl Service {
     sqlx::query!(
       r#"
         update devices
-        set address = $2, slave = $3, seen = $4, pinged = $5
+        set address = $2, port = $3, path = $4, framing = $5, slave = $6, seen = $7, pinged = $8
         where id = $1
       "#,
       id,
-      address,
-      slave,
+      destination.address,
+      destination.port,
+      destination.path,
+      destination.framing as DeviceFraming,
+      destination.slave,
       seen,
       pinged
     )
@@ -275,6 +352,123 @@ impl Service {
     Ok(())
   }
 
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn update_device_identification(
+    &self,
+    id: &str,
+    identification: serde_json::Value,
+  ) -> Result<(), Error> {
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    sqlx::query!(
+      r#"
+        update devices
+        set identification = $2
+        where id = $1
+      "#,
+      id,
+      identification
+    )
+    .execute(&self.pool)
+    .await?;
+
+    tracing::trace!("Updated device identification");
+
+    Ok(())
+  }
+
+  #[tracing::instrument(skip(self, probes))]
+  pub(crate) async fn replace_probes(
+    &self,
+    probes: Vec<Probe>,
+  ) -> Result<(), Error> {
+    let probes_len = probes.len();
+    let mut transaction = self.pool.begin().await?;
+
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    sqlx::query!(
+      r#"
+        delete from probes
+      "#
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if !probes.is_empty() {
+      QueryBuilder::new(
+        "insert into probes (timestamp, address, port, path, framing, slave, result, device)",
+      )
+      .push_values(probes, |mut binder, probe| {
+        binder
+          .push_bind(probe.timestamp)
+          .push_bind(probe.destination.address)
+          .push_bind(probe.destination.port)
+          .push_bind(probe.destination.path)
+          .push_bind(probe.destination.framing)
+          .push_bind(probe.destination.slave)
+          .push_bind(probe.result)
+          .push_bind(probe.device);
+      })
+      .build()
+      .execute(&mut *transaction)
+      .await?;
+    }
+
+    transaction.commit().await?;
+
+    tracing::trace!("Replaced {:?} probes", probes_len);
+
+    Ok(())
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn get_batch_plan(
+    &self,
+    device_id: &str,
+  ) -> Result<Option<BatchPlan>, Error> {
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    let plan = sqlx::query_as!(
+      BatchPlan,
+      r#"
+        select device_id, threshold, max_quantity, updated
+        from batch_plans
+        where device_id = $1
+      "#,
+      device_id
+    )
+    .fetch_optional(&self.pool)
+    .await?;
+
+    tracing::trace!("Fetched batch plan");
+
+    Ok(plan)
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn upsert_batch_plan(
+    &self,
+    plan: BatchPlan,
+  ) -> Result<(), Error> {
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    sqlx::query!(
+      r#"
+        insert into batch_plans (device_id, threshold, max_quantity, updated)
+        values ($1, $2, $3, $4)
+        on conflict (device_id) do update
+        set threshold = $2, max_quantity = $3, updated = $4
+      "#,
+      plan.device_id,
+      plan.threshold,
+      plan.max_quantity,
+      plan.updated
+    )
+    .execute(&self.pool)
+    .await?;
+
+    tracing::trace!("Upserted batch plan");
+
+    Ok(())
+  }
+
   #[tracing::instrument(skip(self))]
   pub(crate) async fn insert_measurement(
     &self,
@@ -283,12 +477,13 @@ impl Service {
     #[allow(clippy::panic)] // NOTE: sqlx thing
     sqlx::query!(
       r#"
-        insert into measurements (source, timestamp, data)
-        values ($1, $2, $3)
+        insert into measurements (source, timestamp, data, group_name)
+        values ($1, $2, $3, $4)
       "#,
       measurement.source,
       measurement.timestamp,
-      measurement.data
+      measurement.data,
+      measurement.group_name
     )
     .execute(&self.pool)
     .await?;
@@ -298,22 +493,126 @@ impl Service {
     Ok(())
   }
 
-  #[tracing::instrument(skip_all, fields(count = measurements.len()))]
-  pub(crate) async fn insert_measurements(
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn get_batch_splits(
     &self,
-    measurements: Vec<Measurement>,
+    device_id: &str,
+  ) -> Result<Vec<BatchSplit>, Error> {
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    let splits = sqlx::query_as!(
+      BatchSplit,
+      r#"
+        select device_id, register_table as "register_table: RegisterTable", address, quantity, updated
+        from batch_splits
+        where device_id = $1
+      "#,
+      device_id
+    )
+    .fetch_all(&self.pool)
+    .await?;
+
+    tracing::trace!("Fetched {:?} batch splits", splits.len());
+
+    Ok(splits)
+  }
+
+  #[tracing::instrument(skip_all, fields(count = splits.len()))]
+  pub(crate) async fn insert_batch_splits(
+    &self,
+    splits: Vec<BatchSplit>,
+  ) -> Result<(), Error> {
+    if splits.is_empty() {
+      return Ok(());
+    }
+
+    QueryBuilder::new(
+      "insert into batch_splits (device_id, register_table, address, quantity, updated)",
+    )
+    .push_values(splits, |mut binder, split| {
+      binder
+        .push_bind(split.device_id)
+        .push_bind(split.register_table)
+        .push_bind(split.address)
+        .push_bind(split.quantity)
+        .push_bind(split.updated);
+    })
+    .push(" on conflict do nothing")
+    .build()
+    .execute(&self.pool)
+    .await?;
+
+    tracing::trace!("Inserted batch splits");
+
+    Ok(())
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn get_gateway_metrics(
+    &self,
+  ) -> Result<Vec<GatewayMetrics>, Error> {
+    #[allow(clippy::panic)] // NOTE: sqlx thing
+    let metrics = sqlx::query_as!(
+      GatewayMetrics,
+      r#"
+        select gateway, metrics, updated
+        from gateway_metrics
+      "#,
+    )
+    .fetch_all(&self.pool)
+    .await?;
+
+    tracing::trace!("Fetched {:?} gateway metrics", metrics.len());
+
+    Ok(metrics)
+  }
+
+  #[tracing::instrument(skip_all, fields(count = metrics.len()))]
+  pub(crate) async fn upsert_gateway_metrics(
+    &self,
+    metrics: Vec<GatewayMetrics>,
   ) -> Result<(), Error> {
-    QueryBuilder::new("insert into measurements (source, timestamp, data)")
-      .push_values(measurements, |mut binder, measurement| {
+    if metrics.is_empty() {
+      return Ok(());
+    }
+
+    QueryBuilder::new("insert into gateway_metrics (gateway, metrics, updated)")
+      .push_values(metrics, |mut binder, metrics| {
         binder
-          .push_bind(measurement.source)
-          .push_bind(measurement.timestamp)
-          .push_bind(measurement.data);
+          .push_bind(metrics.gateway)
+          .push_bind(metrics.metrics)
+          .push_bind(metrics.updated);
       })
+      .push(
+        " on conflict (gateway) do update set metrics = excluded.metrics, updated = excluded.updated",
+      )
       .build()
       .execute(&self.pool)
       .await?;
 
+    tracing::trace!("Upserted gateway metrics");
+
+    Ok(())
+  }
+
+  #[tracing::instrument(skip_all, fields(count = measurements.len()))]
+  pub(crate) async fn insert_measurements(
+    &self,
+    measurements: Vec<Measurement>,
+  ) -> Result<(), Error> {
+    QueryBuilder::new(
+      "insert into measurements (source, timestamp, data, group_name)",
+    )
+    .push_values(measurements, |mut binder, measurement| {
+      binder
+        .push_bind(measurement.source)
+        .push_bind(measurement.timestamp)
+        .push_bind(measurement.data)
+        .push_bind(measurement.group_name);
+    })
+    .build()
+    .execute(&self.pool)
+    .await?;
+
     tracing::trace!("Inserted measurements");
 
     Ok(())
@@ -329,7 +628,7 @@ impl Service {
     let measurements = sqlx::query_as!(
       Measurement,
       r#"
-        select id, source, timestamp, data
+        select id, source, timestamp, data, group_name
         from measurements
         where measurements.id > $1
         order by measurements.id asc
@@ -503,8 +802,39 @@ impl Service {
 }
 
 pub(crate) fn to_db_address(address: IpAddr) -> IpNetwork {
-  #[allow(clippy::unwrap_used)] // NOTE: 24 is valid for ipv4
-  IpNetwork::new(address, 24).unwrap()
+  IpNetwork::from(address)
+}
+
+pub(crate) fn to_db_port(port: u16) -> i32 {
+  port as i32
+}
+
+pub(crate) fn to_db_destination(
+  destination: &modbus::Destination,
+) -> DeviceDestination {
+  let framing = match destination.transport.framing() {
+    modbus::Framing::Tcp => DeviceFraming::Tcp,
+    modbus::Framing::Rtu => DeviceFraming::Rtu,
+  };
+  let (address, port, path) = match &destination.transport {
+    modbus::Transport::Tcp(address)
+    | modbus::Transport::RtuOverTcp(address) => (
+      Some(to_db_address(address.ip())),
+      Some(to_db_port(address.port())),
+      None,
+    ),
+    modbus::Transport::Serial(serial) => {
+      (None, None, Some(serial.path.clone()))
+    }
+  };
+
+  DeviceDestination {
+    address,
+    port,
+    path,
+    framing,
+    slave: to_db_slave(destination.slave),
+  }
 }
 
 pub(crate) fn to_db_slave(slave: Option<u8>) -> Option<i32> {
@@ -515,8 +845,123 @@ pub(crate) fn to_address(db_address: IpNetwork) -> IpAddr {
   db_address.ip()
 }
 
+pub(crate) fn to_transport<
+  'a,
+  TIntoIterator: IntoIterator<Item = &'a modbus::SerialTransport>,
+>(
+  db_address: Option<IpNetwork>,
+  db_port: Option<i32>,
+  db_path: Option<String>,
+  db_framing: DeviceFraming,
+  serial: TIntoIterator,
+) -> Option<modbus::Transport> {
+  match (db_address, db_path) {
+    (Some(db_address), _) => Some(modbus::Transport::tcp(
+      network::to_socket(
+        to_address(db_address),
+        db_port.map_or(network::DEFAULT_PORT, to_port),
+      ),
+      match db_framing {
+        DeviceFraming::Tcp => modbus::Framing::Tcp,
+        DeviceFraming::Rtu => modbus::Framing::Rtu,
+      },
+    )),
+    (None, Some(db_path)) => serial
+      .into_iter()
+      .find(|serial| serial.path == db_path)
+      .cloned()
+      .map(modbus::Transport::Serial),
+    (None, None) => None,
+  }
+}
+
+pub(crate) fn to_port(db_port: i32) -> u16 {
+  db_port as u16
+}
+
 pub(crate) fn to_slave(db_slave: Option<i32>) -> Option<u8> {
   db_slave.map(|slave| slave as u8)
 }
 
+pub(crate) fn to_db_batch_plan(
+  device_id: String,
+  plan: modbus::Plan,
+  updated: DateTime<Utc>,
+) -> BatchPlan {
+  BatchPlan {
+    device_id,
+    threshold: plan.threshold as i32,
+    max_quantity: plan.max_quantity as i32,
+    updated,
+  }
+}
+
+pub(crate) fn to_batch_plan(db_plan: &BatchPlan) -> modbus::Plan {
+  modbus::Plan {
+    threshold: db_plan.threshold as u16,
+    max_quantity: db_plan.max_quantity as u16,
+  }
+}
+
+pub(crate) fn to_db_batch_split(
+  device_id: String,
+  split: modbus::SimpleSpan,
+  updated: DateTime<Utc>,
+) -> BatchSplit {
+  BatchSplit {
+    device_id,
+    register_table: to_db_register_table(split.table),
+    address: split.address as i32,
+    quantity: split.quantity as i32,
+    updated,
+  }
+}
+
+pub(crate) fn to_batch_split(db_split: &BatchSplit) -> modbus::SimpleSpan {
+  modbus::SimpleSpan {
+    table: to_register_table(db_split.register_table),
+    address: db_split.address as u16,
+    quantity: db_split.quantity as u16,
+  }
+}
+
+pub(crate) fn to_db_register_table(
+  table: modbus::RegisterTable,
+) -> RegisterTable {
+  match table {
+    modbus::RegisterTable::Holding => RegisterTable::Holding,
+    modbus::RegisterTable::Input => RegisterTable::Input,
+    modbus::RegisterTable::Coil => RegisterTable::Coil,
+    modbus::RegisterTable::Discrete => RegisterTable::Discrete,
+  }
+}
+
+pub(crate) fn to_register_table(
+  db_table: RegisterTable,
+) -> modbus::RegisterTable {
+  match db_table {
+    RegisterTable::Holding => modbus::RegisterTable::Holding,
+    RegisterTable::Input => modbus::RegisterTable::Input,
+    RegisterTable::Coil => modbus::RegisterTable::Coil,
+    RegisterTable::Discrete => modbus::RegisterTable::Discrete,
+  }
+}
+
+pub(crate) fn to_db_gateway_metrics(
+  metrics: &modbus::GatewayMetrics,
+  updated: DateTime<Utc>,
+) -> Option<GatewayMetrics> {
+  Some(GatewayMetrics {
+    gateway: metrics.gateway.clone(),
+    metrics: serde_json::to_value(metrics).ok()?,
+    updated,
+  })
+}
+
+pub(crate) fn to_gateway_metrics(
+  db_metrics: GatewayMetrics,
+) -> Option<modbus::GatewayMetrics> {
+  serde_json::from_value(db_metrics.metrics).ok()
+}
+
 static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
@@ -5,14 +5,98 @@ use super::span::*;
 // NITPICK: better error handling for span batching
 // NITPICK: macros to implement batch parsing
 
+// NOTE: maximum register quantity allowed in a single modbus read
+pub(crate) const MAX_BATCH_QUANTITY: Quantity = 125;
+
+// NOTE: maximum coil or discrete input quantity allowed in a single modbus
+// read which fits the same number of bytes as MAX_BATCH_QUANTITY registers
+pub(crate) const MAX_BIT_BATCH_QUANTITY: Quantity = 2000;
+
+const BITS_PER_REGISTER: Quantity = 16;
+
+// NOTE: inclusive address range that batches must never cover
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Hole {
+  pub(crate) table: RegisterTable,
+  pub(crate) start: Address,
+  pub(crate) end: Address,
+}
+
+// NOTE: batch threshold and quantity adapted from observed reads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Plan {
+  pub(crate) threshold: u16,
+  pub(crate) max_quantity: Quantity,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Batching {
+  pub(crate) max_quantity: Option<Quantity>,
+  pub(crate) holes: Vec<Hole>,
+}
+
+impl Plan {
+  pub(crate) fn shrink(&self) -> Self {
+    Self {
+      threshold: (self.threshold / 2).max(1),
+      max_quantity: (self.max_quantity / 2).max(1),
+    }
+  }
+
+  // NOTE: growing stops at the configured plan so adapting never bridges
+  // gaps wider than the configured threshold
+  pub(crate) fn grow(&self, step: Quantity, ceiling: Plan) -> Self {
+    Self {
+      threshold: self.threshold.saturating_add(1).min(ceiling.threshold),
+      max_quantity: self
+        .max_quantity
+        .saturating_add(step)
+        .min(ceiling.max_quantity)
+        .min(MAX_BATCH_QUANTITY),
+    }
+  }
+}
+
+// NOTE: quantities are configured and adapted in registers so bit tables
+// get the same number of bytes per read
+fn table_max_quantity(
+  table: RegisterTable,
+  max_quantity: Quantity,
+) -> Quantity {
+  match table {
+    RegisterTable::Holding | RegisterTable::Input => {
+      max_quantity.min(MAX_BATCH_QUANTITY)
+    }
+    RegisterTable::Coil | RegisterTable::Discrete => max_quantity
+      .saturating_mul(BITS_PER_REGISTER)
+      .min(MAX_BIT_BATCH_QUANTITY),
+  }
+}
+
+impl Hole {
+  fn overlaps(
+    &self,
+    table: RegisterTable,
+    start: Address,
+    end: Address,
+  ) -> bool {
+    self.table == table && self.start < end && self.end >= start
+  }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct Batch<TSpan: Span> {
+  pub(crate) table: RegisterTable,
   pub(crate) address: Address,
   pub(crate) quantity: Quantity,
   pub(crate) inner: Vec<TSpan>,
 }
 
 impl<TSpan: Span> Span for Batch<TSpan> {
+  fn table(&self) -> RegisterTable {
+    self.table
+  }
+
   fn address(&self) -> Address {
     self.address
   }
@@ -23,6 +107,10 @@ impl<TSpan: Span> Span for Batch<TSpan> {
 }
 
 impl<TSpan: Span> Span for &Batch<TSpan> {
+  fn table(&self) -> RegisterTable {
+    self.table
+  }
+
   fn address(&self) -> Address {
     self.address
   }
@@ -63,6 +151,7 @@ macro_rules! parse_batch {
     }
 
     Ok(Batch::<TSpan> {
+      table: $self.table,
       address: $self.address,
       quantity: $self.quantity,
       inner,
@@ -75,17 +164,6 @@ macro_rules! impl_batch_span_parser {
     impl<TSpan: Span, TSpanParser: Span + SpanParser<TSpan>>
       SpanParser<Batch<TSpan>> for $type
     {
-      fn parse<TIterator, TIntoIterator>(
-        &self,
-        data: TIntoIterator,
-      ) -> anyhow::Result<Batch<TSpan>>
-      where
-        TIterator: DoubleEndedIterator<Item = u16>,
-        TIntoIterator: IntoIterator<Item = u16, IntoIter = TIterator>,
-      {
-        parse_batch!(self, data, chrono::Utc::now())
-      }
-
       fn parse_with_timestamp<TIterator, TIntoIterator>(
         &self,
         data: TIntoIterator,
@@ -110,9 +188,11 @@ pub(crate) fn batch_spans<
 >(
   spans: TIntoIterator,
   threshold: u16,
+  max_quantity: Quantity,
+  holes: &[Hole],
 ) -> Vec<Batch<TSpan>> {
   let mut spans = spans.into_iter().collect::<Vec<_>>();
-  spans.sort_by_key(|span| span.address());
+  spans.sort_by_key(|span| (span.table(), span.address()));
 
   let mut iter = spans.into_iter();
   let first = match iter.by_ref().next() {
@@ -121,30 +201,53 @@ pub(crate) fn batch_spans<
   };
   let mut batches = Vec::new();
   let mut current = Batch::<TSpan> {
+    table: first.table(),
     address: first.address(),
     quantity: first.quantity(),
     inner: vec![first],
   };
 
   for span in iter {
+    if span.table() != current.table {
+      batches.push(current);
+      current = Batch::<TSpan> {
+        table: span.table(),
+        address: span.address(),
+        quantity: span.quantity(),
+        inner: vec![span],
+      };
+      continue;
+    }
+
+    // NOTE: overlapping spans like a scale factor inside a wider register
+    // have no gap and keep the batch at least as long as it already is
     #[allow(clippy::unwrap_used)] // NOTE: i want this to fail
     let gap = span
       .address()
-      .checked_sub(current.address.checked_add(current.quantity).unwrap())
-      .unwrap();
-    if gap < threshold {
-      #[allow(clippy::unwrap_used)] // NOTE: i want this to fail
-      let quantity = span
-        .address()
-        .checked_add(span.quantity())
-        .unwrap()
-        .checked_sub(current.address)
-        .unwrap();
+      .saturating_sub(current.address.checked_add(current.quantity).unwrap());
+    #[allow(clippy::unwrap_used)] // NOTE: i want this to fail
+    let quantity = span
+      .address()
+      .checked_add(span.quantity())
+      .unwrap()
+      .checked_sub(current.address)
+      .unwrap()
+      .max(current.quantity);
+    #[allow(clippy::unwrap_used)] // NOTE: i want this to fail
+    let end = current.address.checked_add(quantity).unwrap();
+    let bridges_hole = holes
+      .iter()
+      .any(|hole| hole.overlaps(current.table, current.address, end));
+    if gap < threshold
+      && quantity <= table_max_quantity(current.table, max_quantity)
+      && !bridges_hole
+    {
       current.quantity = quantity;
       current.inner.push(span);
     } else {
       batches.push(current);
       current = Batch::<TSpan> {
+        table: span.table(),
         address: span.address(),
         quantity: span.quantity(),
         inner: vec![span],
@@ -155,3 +258,190 @@ pub(crate) fn batch_spans<
 
   batches
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  fn span(
+    table: RegisterTable,
+    address: Address,
+    quantity: Quantity,
+  ) -> SimpleSpan {
+    SimpleSpan {
+      table,
+      address,
+      quantity,
+    }
+  }
+
+  fn ranges(
+    batches: &[Batch<SimpleSpan>],
+  ) -> Vec<(RegisterTable, Address, Quantity)> {
+    batches
+      .iter()
+      .map(|batch| (batch.table, batch.address, batch.quantity))
+      .collect()
+  }
+
+  #[test]
+  fn overlapping_spans_share_a_batch() {
+    let spans = vec![
+      span(RegisterTable::Holding, 10, 2),
+      span(RegisterTable::Holding, 11, 1),
+      span(RegisterTable::Holding, 10, 4),
+      span(RegisterTable::Holding, 14, 2),
+    ];
+    let batches = batch_spans(spans, 1, MAX_BATCH_QUANTITY, &[]);
+    assert_eq!(ranges(&batches), vec![(RegisterTable::Holding, 10, 6)]);
+    assert_eq!(batches[0].inner.len(), 4);
+  }
+
+  #[test]
+  fn register_batches_cap_at_125_registers() {
+    let spans =
+      (0..130).map(|address| span(RegisterTable::Holding, address, 1));
+    let batches = batch_spans(spans, 1, Quantity::MAX, &[]);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Holding, 0, MAX_BATCH_QUANTITY),
+        (RegisterTable::Holding, 125, 5),
+      ]
+    );
+
+    let spans = (0..20).map(|address| span(RegisterTable::Input, address, 1));
+    let batches = batch_spans(spans, 1, 8, &[]);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Input, 0, 8),
+        (RegisterTable::Input, 8, 8),
+        (RegisterTable::Input, 16, 4),
+      ]
+    );
+  }
+
+  #[test]
+  fn bit_batches_cap_at_2000_bits() {
+    let spans = (0..2010).map(|address| span(RegisterTable::Coil, address, 1));
+    let batches = batch_spans(spans, 1, MAX_BATCH_QUANTITY, &[]);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Coil, 0, MAX_BIT_BATCH_QUANTITY),
+        (RegisterTable::Coil, 2000, 10),
+      ]
+    );
+
+    // NOTE: configured quantities count registers worth of bits
+    let spans =
+      (0..40).map(|address| span(RegisterTable::Discrete, address, 1));
+    let batches = batch_spans(spans, 1, 2, &[]);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Discrete, 0, 32),
+        (RegisterTable::Discrete, 32, 8),
+      ]
+    );
+  }
+
+  #[test]
+  fn holes_split_batches() {
+    let spans = vec![
+      span(RegisterTable::Holding, 0, 2),
+      span(RegisterTable::Holding, 6, 2),
+      span(RegisterTable::Holding, 10, 2),
+    ];
+    let holes = [Hole {
+      table: RegisterTable::Holding,
+      start: 3,
+      end: 4,
+    }];
+    let batches = batch_spans(spans.clone(), 10, MAX_BATCH_QUANTITY, &holes);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Holding, 0, 2),
+        (RegisterTable::Holding, 6, 6),
+      ]
+    );
+
+    // NOTE: holes only apply to their own table
+    let holes = [Hole {
+      table: RegisterTable::Input,
+      start: 3,
+      end: 4,
+    }];
+    let batches = batch_spans(spans, 10, MAX_BATCH_QUANTITY, &holes);
+    assert_eq!(ranges(&batches), vec![(RegisterTable::Holding, 0, 12)]);
+  }
+
+  #[test]
+  fn gaps_at_the_threshold_split_batches() {
+    let spans = vec![
+      span(RegisterTable::Holding, 0, 2),
+      span(RegisterTable::Holding, 4, 2),
+      span(RegisterTable::Holding, 9, 1),
+    ];
+    let batches = batch_spans(spans, 3, MAX_BATCH_QUANTITY, &[]);
+    assert_eq!(
+      ranges(&batches),
+      vec![
+        (RegisterTable::Holding, 0, 6),
+        (RegisterTable::Holding, 9, 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn tables_never_share_a_batch() {
+    let spans = vec![
+      span(RegisterTable::Input, 0, 1),
+      span(RegisterTable::Holding, 1, 1),
+      span(RegisterTable::Holding, 0, 1),
+      span(RegisterTable::Coil, 2, 1),
+    ];
+    let batches = batch_spans(spans, 10, MAX_BATCH_QUANTITY, &[]);
+    let tables = batches.iter().map(|batch| batch.table).collect::<Vec<_>>();
+    assert_eq!(batches.len(), 3);
+    assert!(tables.contains(&RegisterTable::Input));
+    assert!(tables.contains(&RegisterTable::Coil));
+    assert!(ranges(&batches).contains(&(RegisterTable::Holding, 0, 2)));
+  }
+
+  #[test]
+  fn plans_shrink_to_one_and_grow_to_the_ceiling() {
+    let ceiling = Plan {
+      threshold: 4,
+      max_quantity: 100,
+    };
+
+    let mut plan = ceiling;
+    for _ in 0..10 {
+      plan = plan.shrink();
+    }
+    assert_eq!(
+      plan,
+      Plan {
+        threshold: 1,
+        max_quantity: 1,
+      }
+    );
+
+    for _ in 0..100 {
+      plan = plan.grow(8, ceiling);
+    }
+    assert_eq!(plan, ceiling);
+
+    let unbounded = Plan {
+      threshold: u16::MAX,
+      max_quantity: Quantity::MAX,
+    };
+    let plan = unbounded.grow(8, unbounded);
+    assert_eq!(plan.threshold, u16::MAX);
+    assert_eq!(plan.max_quantity, MAX_BATCH_QUANTITY);
+  }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::*;
+
+use super::connection::Exception;
+use super::mbap::{encode_frame, read_frame};
+use super::record::Record;
+use super::register::{MeasurementRegister, RegisterValueStorage};
+use super::span::Span;
+
+// NOTE: read holding and input registers are the only functions served
+const READ_FUNCTIONS: [u8; 2] = [0x03, 0x04];
+
+const MAX_READ_QUANTITY: u16 = 125;
+
+// NOTE: pause after a failed accept so persistent failures don't spin
+const ACCEPT_BACKOFF: std::time::Duration =
+  std::time::Duration::from_millis(100);
+
+type TaskHandle = tokio::task::JoinHandle<()>;
+
+// NOTE: virtual address to register word of a unit and when the device
+// behind the unit was last published
+#[derive(Debug)]
+struct UnitValues {
+  published: tokio::time::Instant,
+  words: BTreeMap<u16, u16>,
+}
+
+// NOTE: modbus tcp server exposing the latest measurements of devices
+// through a virtual register map per unit id
+#[derive(Clone, Debug)]
+pub(crate) struct Concentrator {
+  address: SocketAddr,
+  max_age: Option<std::time::Duration>,
+  units: Arc<Vec<config::ConcentratorUnit>>,
+  values: Arc<Mutex<HashMap<u8, UnitValues>>>,
+  handle: Arc<Mutex<Option<TaskHandle>>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ServeError {
+  #[error("Failed binding listener")]
+  Bind(#[from] std::io::Error),
+}
+
+impl Concentrator {
+  pub(crate) fn new(config: config::Concentrator) -> Self {
+    Self {
+      address: config.address,
+      max_age: config.max_age.and_then(|max_age| max_age.to_std().ok()),
+      units: Arc::new(config.units),
+      values: Arc::new(Mutex::new(HashMap::new())),
+      handle: Arc::new(Mutex::new(None)),
+    }
+  }
+
+  // NOTE: registers are encoded the same way they would be written so
+  // scaled values get served in the width of their register kind
+  pub(crate) async fn publish(
+    &self,
+    id: &str,
+    registers: &[MeasurementRegister<RegisterValueStorage>],
+  ) {
+    let registers = registers
+      .iter()
+      .map(|register| (register.name.as_str(), register))
+      .collect::<HashMap<_, _>>();
+
+    let published = tokio::time::Instant::now();
+    let mut values = self.values.clone().lock_owned().await;
+    for unit in self.units.iter().filter(|unit| unit.device == id) {
+      let unit_values = values.entry(unit.unit).or_insert_with(|| UnitValues {
+        published,
+        words: BTreeMap::new(),
+      });
+      unit_values.published = published;
+      for (name, address) in unit.registers.iter() {
+        let Some(register) = registers.get(name.as_str()) else {
+          continue;
+        };
+        let addresses =
+          (*address..=u16::MAX).take(usize::from(register.quantity()));
+        // NOTE: words without data get cleared instead of served as zero
+        if register.storage.is_encodable() {
+          unit_values.words.extend(addresses.zip(register.values()));
+        } else {
+          for address in addresses {
+            unit_values.words.remove(&address);
+          }
+        }
+      }
+    }
+  }
+
+  pub(crate) async fn serve(&self) -> Result<(), ServeError> {
+    let mut handle = self.handle.clone().lock_owned().await;
+    if handle.is_some() {
+      return Ok(());
+    }
+
+    let listener = TcpListener::bind(self.address).await?;
+    tracing::info!("Concentrator listening on {}", self.address);
+
+    let concentrator = self.clone();
+    *handle = Some(tokio::spawn(async move {
+      concentrator.accept(listener).await;
+    }));
+
+    Ok(())
+  }
+
+  pub(crate) async fn stop(&self) {
+    let mut handle = self.handle.clone().lock_owned().await;
+    if let Some(handle) = handle.take() {
+      handle.abort();
+    }
+  }
+
+  async fn accept(self, listener: TcpListener) {
+    loop {
+      match listener.accept().await {
+        Ok((stream, peer)) => {
+          tracing::debug!("Accepted concentrator connection from {}", peer);
+          let concentrator = self.clone();
+          tokio::spawn(async move {
+            if let Err(error) = concentrator.connection(stream).await {
+              tracing::debug!(
+                "Closed concentrator connection from {} {}",
+                peer,
+                error
+              );
+            }
+          });
+        }
+        Err(error) => {
+          tracing::warn!("Failed accepting concentrator connection {}", error);
+          tokio::time::sleep(ACCEPT_BACKOFF).await;
+        }
+      }
+    }
+  }
+
+  async fn connection(
+    &self,
+    mut stream: TcpStream,
+  ) -> Result<(), std::io::Error> {
+    loop {
+      let frame = read_frame(&mut stream).await?;
+      let Some(&function) = frame.pdu.first() else {
+        continue;
+      };
+      let pdu = match self.read(frame.unit, &frame.pdu).await {
+        Ok(pdu) => pdu,
+        Err(exception) => vec![function | 0x80, exception.code()],
+      };
+      let adu = encode_frame(frame.transaction, frame.unit, &pdu)?;
+      stream.write_all(&adu).await?;
+    }
+  }
+
+  async fn read(&self, unit: u8, pdu: &[u8]) -> Result<Vec<u8>, Exception> {
+    let (function, data) = match pdu {
+      [function, data @ ..] if READ_FUNCTIONS.contains(function) => {
+        (*function, data)
+      }
+      _ => return Err(Exception::IllegalFunction),
+    };
+    let (address, quantity) = match data {
+      [a0, a1, q0, q1] => (
+        u16::from_be_bytes([*a0, *a1]),
+        u16::from_be_bytes([*q0, *q1]),
+      ),
+      _ => return Err(Exception::IllegalDataValue),
+    };
+    if quantity == 0 || quantity > MAX_READ_QUANTITY {
+      return Err(Exception::IllegalDataValue);
+    }
+    if address.checked_add(quantity.saturating_sub(1)).is_none() {
+      return Err(Exception::IllegalDataAddress);
+    }
+    if !self.units.iter().any(|config| config.unit == unit) {
+      return Err(Exception::GatewayPathUnavailable);
+    }
+
+    let values = self.values.clone().lock_owned().await;
+    // NOTE: nothing was measured from the device behind the unit yet
+    let unit_values =
+      values.get(&unit).ok_or(Exception::GatewayTargetDevice)?;
+    // NOTE: the device stopped being measured so its values are stale
+    if self
+      .max_age
+      .is_some_and(|max_age| unit_values.published.elapsed() > max_age)
+    {
+      return Err(Exception::GatewayTargetDevice);
+    }
+
+    let mut response = Vec::with_capacity(
+      usize::from(quantity).saturating_mul(2).saturating_add(2),
+    );
+    response.push(function);
+    response.push(quantity.saturating_mul(2) as u8);
+    for address in (address..=u16::MAX).take(usize::from(quantity)) {
+      let word = unit_values
+        .words
+        .get(&address)
+        .ok_or(Exception::IllegalDataAddress)?;
+      response.extend_from_slice(&word.to_be_bytes());
+    }
+
+    Ok(response)
+  }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  use rust_decimal::Decimal;
+
+  use super::super::encoding::Endianness;
+  use super::super::register::{
+    apply_scale_factors, MissingValuePolicy, NumericRegisterKind,
+    RegisterKindStorage,
+  };
+  use super::super::span::{RegisterTable, SpanParser};
+
+  fn kind(multiplier: Option<Decimal>) -> NumericRegisterKind {
+    NumericRegisterKind {
+      multiplier,
+      offset: None,
+      endianness: Endianness::Abcd,
+      sentinels: Some(Vec::new()),
+      missing: MissingValuePolicy::Null,
+    }
+  }
+
+  fn power_register() -> MeasurementRegister<RegisterKindStorage> {
+    MeasurementRegister::<RegisterKindStorage> {
+      table: RegisterTable::Holding,
+      address: 0,
+      storage: RegisterKindStorage::U32(kind(Some(Decimal::new(1, 1)))),
+      name: "power".to_string(),
+      scale_factor: Some("scaleFactor2".to_string()),
+      internal: false,
+    }
+  }
+
+  fn scale_factor_register() -> MeasurementRegister<RegisterKindStorage> {
+    MeasurementRegister::<RegisterKindStorage> {
+      table: RegisterTable::Holding,
+      address: 2,
+      storage: RegisterKindStorage::S16(NumericRegisterKind {
+        sentinels: Some(vec![Decimal::from(i16::MIN)]),
+        ..kind(None)
+      }),
+      name: "scaleFactor2".to_string(),
+      scale_factor: None,
+      internal: true,
+    }
+  }
+
+  fn concentrator(max_age: Option<chrono::Duration>) -> Concentrator {
+    Concentrator::new(config::Concentrator {
+      address: "127.0.0.1:0".parse().unwrap(),
+      max_age,
+      units: vec![config::ConcentratorUnit {
+        unit: 7,
+        device: "meter".to_string(),
+        registers: vec![("power".to_string(), 100)],
+      }],
+    })
+  }
+
+  fn measured_with(
+    power: [u16; 2],
+    scale_factor: u16,
+  ) -> Vec<MeasurementRegister<RegisterValueStorage>> {
+    apply_scale_factors(vec![
+      power_register()
+        .parse_with_timestamp(power, chrono::Utc::now())
+        .unwrap(),
+      scale_factor_register()
+        .parse_with_timestamp(vec![scale_factor], chrono::Utc::now())
+        .unwrap(),
+    ])
+  }
+
+  fn measured() -> Vec<MeasurementRegister<RegisterValueStorage>> {
+    measured_with([0x0000, 0x04D2], 0x0002)
+  }
+
+  fn words(pdu: &[u8]) -> Vec<u16> {
+    pdu
+      .get(2..)
+      .unwrap()
+      .chunks_exact(2)
+      .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+      .collect()
+  }
+
+  #[tokio::test]
+  async fn served_values_decode_to_measured_values() {
+    let concentrator = concentrator(None);
+    let registers = measured();
+    let value = registers[0].storage.numeric().unwrap().value;
+    assert_eq!(value, Some(Decimal::new(12_340, 0)));
+
+    concentrator.publish("meter", &registers).await;
+    let pdu = concentrator.read(7, &[0x03, 0, 100, 0, 2]).await.unwrap();
+    assert_eq!(pdu[..2], [0x03, 0x04]);
+
+    let served = power_register()
+      .parse_with_timestamp(words(&pdu), chrono::Utc::now())
+      .unwrap();
+    assert_eq!(served.storage.numeric().unwrap().value, value);
+  }
+
+  #[tokio::test]
+  async fn unpublished_and_stale_units_are_unavailable() {
+    let concentrator = concentrator(Some(chrono::Duration::milliseconds(20)));
+    let request = [0x04, 0, 100, 0, 2];
+
+    let error = concentrator.read(7, &request).await.unwrap_err();
+    assert_eq!(error, Exception::GatewayTargetDevice);
+    let error = concentrator.read(8, &request).await.unwrap_err();
+    assert_eq!(error, Exception::GatewayPathUnavailable);
+
+    concentrator.publish("meter", &measured()).await;
+    assert!(concentrator.read(7, &request).await.is_ok());
+
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let error = concentrator.read(7, &request).await.unwrap_err();
+    assert_eq!(error, Exception::GatewayTargetDevice);
+
+    concentrator.publish("meter", &measured()).await;
+    assert!(concentrator.read(7, &request).await.is_ok());
+  }
+
+  #[tokio::test]
+  async fn missing_and_overflowing_values_are_not_served_as_zero() {
+    let concentrator = concentrator(None);
+    let request = [0x03, 0, 100, 0, 2];
+    concentrator.publish("meter", &measured()).await;
+    assert!(concentrator.read(7, &request).await.is_ok());
+
+    // NOTE: scale factor sentinel leaves the scaled value missing
+    let registers = measured_with([0x0000, 0x04D2], 0x8000);
+    assert_eq!(registers[0].storage.numeric().unwrap().value, None);
+    concentrator.publish("meter", &registers).await;
+    let error = concentrator.read(7, &request).await.unwrap_err();
+    assert_eq!(error, Exception::IllegalDataAddress);
+
+    concentrator.publish("meter", &measured()).await;
+    assert!(concentrator.read(7, &request).await.is_ok());
+
+    // NOTE: scaled value no longer fits the u32 register
+    let registers = measured_with([0x0100, 0x0000], 0x0003);
+    assert!(!registers[0].storage.is_encodable());
+    concentrator.publish("meter", &registers).await;
+    let error = concentrator.read(7, &request).await.unwrap_err();
+    assert_eq!(error, Exception::IllegalDataAddress);
+  }
+}
@@ -1,55 +1,150 @@
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 
 use futures_time::future::FutureExt;
 use thiserror::Error;
 use tokio::net::TcpStream;
 use tokio_modbus::{
-  client::{Context, Writer},
+  client::{Client, Context, Writer},
   prelude::Reader,
   slave::SlaveContext,
-  Slave,
+  Request, Response, Slave,
+};
+use tokio_serial::SerialPortBuilderExt;
+
+use super::{
+  identification::*,
+  mbap::{ExceptionResponse, MbapClient},
+  record::SimpleRecord,
+  span::{RegisterTable, SimpleSpan},
 };
 
-use super::{record::SimpleRecord, span::SimpleSpan};
+// NOTE: devices keep answering with more to follow sometimes
+const MAX_IDENTIFICATION_PAGES: usize = 4;
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum DataBits {
+  Five,
+  Six,
+  Seven,
+  Eight,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum Parity {
+  None,
+  Odd,
+  Even,
+}
+
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum StopBits {
+  One,
+  Two,
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub(crate) struct SerialTransport {
+  pub(crate) path: String,
+  pub(crate) baud_rate: u32,
+  pub(crate) data_bits: DataBits,
+  pub(crate) parity: Parity,
+  pub(crate) stop_bits: StopBits,
+  pub(crate) delay: chrono::Duration,
+}
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum Framing {
+  Tcp,
+  Rtu,
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub(crate) enum Transport {
+  Tcp(SocketAddr),
+  RtuOverTcp(SocketAddr),
+  Serial(SerialTransport),
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub(crate) struct Destination {
-  pub(crate) address: SocketAddr,
+  pub(crate) transport: Transport,
   pub(crate) slave: Option<u8>,
 }
 
 impl Destination {
   pub(crate) fn slaves_for(
-    address: SocketAddr,
+    transport: Transport,
+    slaves: Option<Vec<u8>>,
   ) -> impl Iterator<Item = Destination> {
-    (Slave::min_device().0..Slave::max_device().0).map(move |slave| {
-      Destination {
-        address,
+    slaves
+      .unwrap_or_else(|| {
+        (Slave::min_device().0..Slave::max_device().0).collect()
+      })
+      .into_iter()
+      .filter(|slave| {
+        *slave >= Slave::min_device().0 && *slave <= Slave::max_device().0
+      })
+      .map(move |slave| Destination {
+        transport: transport.clone(),
         slave: Some(slave),
-      }
-    })
+      })
   }
 
-  pub(crate) fn standalone_for(address: SocketAddr) -> Destination {
+  pub(crate) fn standalone_for(transport: Transport) -> Destination {
     Destination {
-      address,
+      transport,
       slave: None,
     }
   }
 }
 
+impl Transport {
+  pub(crate) fn tcp(address: SocketAddr, framing: Framing) -> Self {
+    match framing {
+      Framing::Tcp => Transport::Tcp(address),
+      Framing::Rtu => Transport::RtuOverTcp(address),
+    }
+  }
+
+  pub(crate) fn framing(&self) -> Framing {
+    match self {
+      Transport::Tcp(_) => Framing::Tcp,
+      Transport::RtuOverTcp(_) | Transport::Serial(_) => Framing::Rtu,
+    }
+  }
+
+  pub(crate) fn supports_standalone(&self) -> bool {
+    matches!(self, Transport::Tcp(_))
+  }
+}
+
 pub(crate) type ReadResponse = Vec<u16>;
 pub(crate) type WriteResponse = ();
 
 #[derive(Debug)]
 pub(crate) struct Connection {
-  address: SocketAddr,
+  transport: Transport,
+  delay: Option<chrono::Duration>,
   ctx: Option<Context>,
+  last: Option<tokio::time::Instant>,
+  drains: Arc<AtomicU64>,
 }
 
 impl Connection {
-  pub(crate) fn new(address: SocketAddr) -> Self {
-    Self { address, ctx: None }
+  pub(crate) fn new(
+    transport: Transport,
+    delay: Option<chrono::Duration>,
+    drains: Arc<AtomicU64>,
+  ) -> Self {
+    Self {
+      transport,
+      delay,
+      ctx: None,
+      last: None,
+      drains,
+    }
   }
 
   pub(crate) async fn ensure_connected(&mut self) -> Result<(), ConnectError> {
@@ -66,14 +161,46 @@ pub(crate) enum ConnectError {
   #[error("Failed to connect")]
   Connect(#[from] std::io::Error),
 
+  #[error("Failed to open serial port")]
+  Serial(#[from] tokio_serial::Error),
+
   #[error("Wrong slave number")]
   Slave,
 }
 
 impl Connection {
   async fn reconnect(&mut self) -> Result<&mut Context, ConnectError> {
-    let stream = TcpStream::connect(self.address).await?;
-    let ctx = tokio_modbus::prelude::tcp::attach(stream);
+    let ctx = match &self.transport {
+      Transport::Tcp(address) => {
+        let stream = TcpStream::connect(address).await?;
+        let client = MbapClient::new(stream, self.drains.clone());
+        Context::from(Box::new(client) as Box<dyn Client>)
+      }
+      Transport::RtuOverTcp(address) => {
+        let stream = TcpStream::connect(address).await?;
+        tokio_modbus::prelude::rtu::attach(stream)
+      }
+      Transport::Serial(serial) => {
+        let stream = tokio_serial::new(serial.path.as_str(), serial.baud_rate)
+          .data_bits(match serial.data_bits {
+            DataBits::Five => tokio_serial::DataBits::Five,
+            DataBits::Six => tokio_serial::DataBits::Six,
+            DataBits::Seven => tokio_serial::DataBits::Seven,
+            DataBits::Eight => tokio_serial::DataBits::Eight,
+          })
+          .parity(match serial.parity {
+            Parity::None => tokio_serial::Parity::None,
+            Parity::Odd => tokio_serial::Parity::Odd,
+            Parity::Even => tokio_serial::Parity::Even,
+          })
+          .stop_bits(match serial.stop_bits {
+            StopBits::One => tokio_serial::StopBits::One,
+            StopBits::Two => tokio_serial::StopBits::Two,
+          })
+          .open_native_async()?;
+        tokio_modbus::prelude::rtu::attach(stream)
+      }
+    };
 
     tracing::trace!("Connected");
 
@@ -84,6 +211,112 @@ impl Connection {
   }
 }
 
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Exception {
+  IllegalFunction,
+  IllegalDataAddress,
+  IllegalDataValue,
+  ServerDeviceFailure,
+  Acknowledge,
+  ServerDeviceBusy,
+  MemoryParityError,
+  GatewayPathUnavailable,
+  GatewayTargetDevice,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ExceptionClass {
+  // NOTE: the request itself is wrong so retrying it will not help
+  Rejected,
+  Busy,
+  Gateway,
+  Device,
+}
+
+impl Exception {
+  pub(crate) fn from_code(code: u8) -> Option<Self> {
+    match code {
+      0x01 => Some(Exception::IllegalFunction),
+      0x02 => Some(Exception::IllegalDataAddress),
+      0x03 => Some(Exception::IllegalDataValue),
+      0x04 => Some(Exception::ServerDeviceFailure),
+      0x05 => Some(Exception::Acknowledge),
+      0x06 => Some(Exception::ServerDeviceBusy),
+      0x08 => Some(Exception::MemoryParityError),
+      0x0A => Some(Exception::GatewayPathUnavailable),
+      0x0B => Some(Exception::GatewayTargetDevice),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn code(&self) -> u8 {
+    match self {
+      Exception::IllegalFunction => 0x01,
+      Exception::IllegalDataAddress => 0x02,
+      Exception::IllegalDataValue => 0x03,
+      Exception::ServerDeviceFailure => 0x04,
+      Exception::Acknowledge => 0x05,
+      Exception::ServerDeviceBusy => 0x06,
+      Exception::MemoryParityError => 0x08,
+      Exception::GatewayPathUnavailable => 0x0A,
+      Exception::GatewayTargetDevice => 0x0B,
+    }
+  }
+
+  // NOTE: tcp framing goes through MbapClient which returns a typed exception
+  // response but the tokio-modbus rtu client keeps its exception response type
+  // private so rtu framing still has to be matched by its debug output
+  fn from_io_error(error: &std::io::Error) -> Option<Self> {
+    if error.kind() != std::io::ErrorKind::Other {
+      return None;
+    }
+
+    if let Some(response) = error
+      .get_ref()
+      .and_then(|inner| inner.downcast_ref::<ExceptionResponse>())
+    {
+      return Some(response.exception);
+    }
+
+    let debug = format!("{:?}", error.get_ref()?);
+    let name = debug
+      .split("exception: ")
+      .nth(1)?
+      .trim_end_matches([' ', '}']);
+    match name {
+      "IllegalFunction" => Some(Exception::IllegalFunction),
+      "IllegalDataAddress" => Some(Exception::IllegalDataAddress),
+      "IllegalDataValue" => Some(Exception::IllegalDataValue),
+      "ServerDeviceFailure" => Some(Exception::ServerDeviceFailure),
+      "Acknowledge" => Some(Exception::Acknowledge),
+      "ServerDeviceBusy" => Some(Exception::ServerDeviceBusy),
+      "MemoryParityError" => Some(Exception::MemoryParityError),
+      "GatewayPathUnavailable" => Some(Exception::GatewayPathUnavailable),
+      "GatewayTargetDevice" => Some(Exception::GatewayTargetDevice),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn class(&self) -> ExceptionClass {
+    match self {
+      Exception::IllegalFunction
+      | Exception::IllegalDataAddress
+      | Exception::IllegalDataValue => ExceptionClass::Rejected,
+      Exception::Acknowledge | Exception::ServerDeviceBusy => {
+        ExceptionClass::Busy
+      }
+      Exception::GatewayPathUnavailable | Exception::GatewayTargetDevice => {
+        ExceptionClass::Gateway
+      }
+      Exception::ServerDeviceFailure | Exception::MemoryParityError => {
+        ExceptionClass::Device
+      }
+    }
+  }
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum ReadError {
   #[error("Failed connecting")]
@@ -92,10 +325,36 @@ pub(crate) enum ReadError {
   #[error("Failed reading")]
   Read(std::io::Error),
 
+  #[error("Server responded with exception {0:?}")]
+  Exception(Exception),
+
+  #[error("Request is not supported over {0:?} framing")]
+  Unsupported(Framing),
+
   #[error("Connection timed out")]
   Timeout(std::io::Error),
 }
 
+impl ReadError {
+  pub(crate) fn exception(&self) -> Option<Exception> {
+    match self {
+      ReadError::Exception(exception) => Some(*exception),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn is_congestion(&self) -> bool {
+    match self {
+      ReadError::Read(error) => error.kind() == std::io::ErrorKind::InvalidData,
+      ReadError::Exception(exception) => {
+        exception.class() == ExceptionClass::Busy
+      }
+      ReadError::Timeout(_) => true,
+      ReadError::Connection(_) | ReadError::Unsupported(_) => false,
+    }
+  }
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum WriteError {
   #[error("Failed connecting")]
@@ -104,43 +363,103 @@ pub(crate) enum WriteError {
   #[error("Failed reading")]
   Read(std::io::Error),
 
+  #[error("Server responded with exception {0:?}")]
+  Exception(Exception),
+
   #[error("Connection timed out")]
   Timeout(std::io::Error),
+
+  #[error("Register table {0:?} is read-only")]
+  ReadOnly(RegisterTable),
 }
 
 impl Connection {
-  #[tracing::instrument(skip(self), fields(address = ?self.address))]
+  #[tracing::instrument(skip(self), fields(transport = ?self.transport))]
   pub(crate) async fn read(
     &mut self,
     slave: Option<u8>,
     span: SimpleSpan,
     timeout: chrono::Duration,
   ) -> Result<ReadResponse, ReadError> {
+    self.wait_for_delay().await;
     let response = self
       .simple_read_impl(slave, span, timeout_from_chrono(timeout))
-      .await?;
+      .await;
+    self.last = Some(tokio::time::Instant::now());
+    let response = response?;
 
     tracing::trace!("Simple read successful");
 
     Ok(response)
   }
 
-  #[tracing::instrument(skip(self), fields(address = ?self.address))]
+  #[tracing::instrument(skip(self), fields(transport = ?self.transport))]
+  pub(crate) async fn identify(
+    &mut self,
+    slave: Option<u8>,
+    timeout: chrono::Duration,
+  ) -> Result<Identification, ReadError> {
+    // NOTE: tokio-modbus can't tell rtu frame lengths of custom functions
+    if self.transport.framing() != Framing::Tcp {
+      return Err(ReadError::Unsupported(self.transport.framing()));
+    }
+
+    let mut identification = Identification::default();
+    let mut object = 0x00;
+    for _ in 0..MAX_IDENTIFICATION_PAGES {
+      self.wait_for_delay().await;
+      let response = self
+        .simple_identify_impl(slave, object, timeout_from_chrono(timeout))
+        .await;
+      self.last = Some(tokio::time::Instant::now());
+      let page = response?;
+      identification.extend(page.objects);
+      match page.next {
+        Some(next) if next > object => object = next,
+        _ => break,
+      }
+    }
+
+    tracing::trace!("Identification successful");
+
+    Ok(identification)
+  }
+
+  #[tracing::instrument(skip(self), fields(transport = ?self.transport))]
   pub(crate) async fn write(
     &mut self,
     slave: Option<u8>,
     record: SimpleRecord,
     timeout: chrono::Duration,
   ) -> Result<WriteResponse, WriteError> {
-    self
+    self.wait_for_delay().await;
+    let response = self
       .simple_write_impl(slave, record, timeout_from_chrono(timeout))
-      .await?;
+      .await;
+    self.last = Some(tokio::time::Instant::now());
+    response?;
 
     tracing::trace!("Simple read successful");
 
     Ok(())
   }
 
+  async fn wait_for_delay(&mut self) {
+    let delay = match (&self.transport, self.delay) {
+      (_, Some(delay)) => delay,
+      (Transport::Serial(serial), None) => serial.delay,
+      (Transport::Tcp(_) | Transport::RtuOverTcp(_), None) => return,
+    };
+
+    if let Some(deadline) = self
+      .last
+      .zip(delay.to_std().ok())
+      .and_then(|(last, delay)| last.checked_add(delay))
+    {
+      tokio::time::sleep_until(deadline).await;
+    }
+  }
+
   async fn simple_read_impl(
     &mut self,
     slave: Option<u8>,
@@ -157,6 +476,36 @@ impl Connection {
       }
     };
 
+    // NOTE: gateway faults might be cleared by reconnecting to the gateway
+    if matches!(response, Err(ReadError::Connection(_) | ReadError::Read(_)))
+      || matches!(
+        response,
+        Err(ReadError::Exception(exception))
+          if exception.class() == ExceptionClass::Gateway
+      )
+    {
+      self.ctx = None;
+    }
+
+    response
+  }
+
+  async fn simple_identify_impl(
+    &mut self,
+    slave: Option<u8>,
+    object: u8,
+    timeout: futures_time::time::Duration,
+  ) -> Result<IdentificationPage, ReadError> {
+    let response = match &mut self.ctx {
+      Some(ctx) => {
+        Self::simple_identify_impl_connected(ctx, slave, object, timeout).await
+      }
+      None => {
+        let ctx = self.reconnect().await?;
+        Self::simple_identify_impl_connected(ctx, slave, object, timeout).await
+      }
+    };
+
     if matches!(response, Err(ReadError::Connection(_) | ReadError::Read(_))) {
       self.ctx = None;
     }
@@ -206,23 +555,93 @@ impl Connection {
       ctx.set_slave(Slave::tcp_device())
     }
 
-    match ctx
-      .read_holding_registers(span.address, span.quantity)
-      .timeout(timeout)
-      .await
-    {
+    let response = async {
+      match span.table {
+        RegisterTable::Holding => {
+          ctx
+            .read_holding_registers(span.address, span.quantity)
+            .await
+        }
+        RegisterTable::Input => {
+          ctx.read_input_registers(span.address, span.quantity).await
+        }
+        RegisterTable::Coil => ctx
+          .read_coils(span.address, span.quantity)
+          .await
+          .map(bits_to_registers),
+        RegisterTable::Discrete => ctx
+          .read_discrete_inputs(span.address, span.quantity)
+          .await
+          .map(bits_to_registers),
+      }
+    };
+
+    match response.timeout(timeout).await {
       Err(timeout_error) => Err(ReadError::Timeout(timeout_error)),
-      Ok(Err(connection_error)) => Err(ReadError::Read(connection_error)),
+      Ok(Err(connection_error)) => {
+        match Exception::from_io_error(&connection_error) {
+          Some(exception) => Err(ReadError::Exception(exception)),
+          None => Err(ReadError::Read(connection_error)),
+        }
+      }
       Ok(Ok(response)) => Ok(response),
     }
   }
 
+  async fn simple_identify_impl_connected(
+    ctx: &mut Context,
+    slave: Option<u8>,
+    object: u8,
+    timeout: futures_time::time::Duration,
+  ) -> Result<IdentificationPage, ReadError> {
+    if let Some(slave) = slave {
+      if slave < Slave::min_device().0 || slave > Slave::max_device().0 {
+        return Err(ReadError::Connection(ConnectError::Slave));
+      }
+
+      ctx.set_slave(Slave(slave))
+    } else {
+      ctx.set_slave(Slave::tcp_device())
+    }
+
+    let response = ctx.call(Request::Custom(
+      READ_DEVICE_IDENTIFICATION,
+      make_identification_request(object).into(),
+    ));
+
+    match response.timeout(timeout).await {
+      Err(timeout_error) => Err(ReadError::Timeout(timeout_error)),
+      Ok(Err(connection_error)) => {
+        match Exception::from_io_error(&connection_error) {
+          Some(exception) => Err(ReadError::Exception(exception)),
+          None => Err(ReadError::Read(connection_error)),
+        }
+      }
+      Ok(Ok(Response::Custom(READ_DEVICE_IDENTIFICATION, data))) => {
+        parse_identification_response(&data).ok_or_else(|| {
+          ReadError::Read(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Invalid device identification response",
+          ))
+        })
+      }
+      Ok(Ok(response)) => Err(ReadError::Read(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Unexpected device identification response {:?}", response),
+      ))),
+    }
+  }
+
   async fn simple_write_impl_connected(
     ctx: &mut Context,
     slave: Option<u8>,
     record: SimpleRecord,
     timeout: futures_time::time::Duration,
   ) -> Result<WriteResponse, WriteError> {
+    if matches!(record.table, RegisterTable::Input | RegisterTable::Discrete) {
+      return Err(WriteError::ReadOnly(record.table));
+    }
+
     if let Some(slave) = slave {
       if slave < Slave::min_device().0 || slave > Slave::max_device().0 {
         return Err(WriteError::Connection(ConnectError::Slave));
@@ -233,20 +652,110 @@ impl Connection {
       ctx.set_slave(Slave::tcp_device())
     }
 
-    match ctx
-      .write_multiple_registers(record.address, &record.values)
-      .timeout(timeout)
-      .await
-    {
+    let response = async {
+      if record.table == RegisterTable::Coil {
+        ctx
+          .write_multiple_coils(
+            record.address,
+            &registers_to_bits(&record.values),
+          )
+          .await
+      } else {
+        ctx
+          .write_multiple_registers(record.address, &record.values)
+          .await
+      }
+    };
+
+    match response.timeout(timeout).await {
       Err(timeout_error) => Err(WriteError::Timeout(timeout_error)),
-      Ok(Err(connection_error)) => Err(WriteError::Read(connection_error)),
+      Ok(Err(connection_error)) => {
+        match Exception::from_io_error(&connection_error) {
+          Some(exception) => Err(WriteError::Exception(exception)),
+          None => Err(WriteError::Read(connection_error)),
+        }
+      }
       Ok(Ok(_)) => Ok(()),
     }
   }
 }
 
+fn bits_to_registers(bits: Vec<bool>) -> Vec<u16> {
+  bits.into_iter().map(u16::from).collect()
+}
+
+fn registers_to_bits(registers: &[u16]) -> Vec<bool> {
+  registers.iter().map(|register| *register != 0).collect()
+}
+
 fn timeout_from_chrono(
   timeout: chrono::Duration,
 ) -> futures_time::time::Duration {
   futures_time::time::Duration::from_millis(timeout.num_milliseconds() as u64)
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+  fn crc(data: &[u8]) -> [u8; 2] {
+    let crc = data.iter().fold(0xFFFFu16, |crc, byte| {
+      (0..8).fold(crc ^ u16::from(*byte), |crc, _| {
+        if crc & 1 == 1 {
+          (crc >> 1) ^ 0xA001
+        } else {
+          crc >> 1
+        }
+      })
+    });
+    crc.to_le_bytes()
+  }
+
+  const EXCEPTIONS: [Exception; 9] = [
+    Exception::IllegalFunction,
+    Exception::IllegalDataAddress,
+    Exception::IllegalDataValue,
+    Exception::ServerDeviceFailure,
+    Exception::Acknowledge,
+    Exception::ServerDeviceBusy,
+    Exception::MemoryParityError,
+    Exception::GatewayPathUnavailable,
+    Exception::GatewayTargetDevice,
+  ];
+
+  // NOTE: guards the debug output parsing against tokio-modbus changes
+  #[tokio::test]
+  async fn rtu_exceptions_are_recognised() {
+    let (client, mut server) = tokio::io::duplex(64);
+    let server = tokio::spawn(async move {
+      for exception in EXCEPTIONS {
+        let mut request = [0u8; 8];
+        server.read_exact(&mut request).await.unwrap();
+        let mut response =
+          vec![request[0], request[1] | 0x80, exception.code()];
+        response.extend_from_slice(&crc(&response));
+        server.write_all(&response).await.unwrap();
+      }
+    });
+
+    let mut ctx = tokio_modbus::prelude::rtu::attach(client);
+    ctx.set_slave(Slave(1));
+    for exception in EXCEPTIONS {
+      let error = ctx.read_holding_registers(0, 1).await.unwrap_err();
+      assert_eq!(Exception::from_io_error(&error), Some(exception));
+    }
+
+    server.await.unwrap();
+  }
+
+  #[test]
+  fn other_io_errors_are_not_exceptions() {
+    let error = std::io::Error::other("exception");
+    assert_eq!(Exception::from_io_error(&error), None);
+    let error = std::io::Error::from(std::io::ErrorKind::TimedOut);
+    assert_eq!(Exception::from_io_error(&error), None);
+  }
+}
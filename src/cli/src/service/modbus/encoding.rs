@@ -1,5 +1,103 @@
 use itertools::Itertools;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endianness {
+  Abcd,
+  Cdab,
+  Badc,
+  Dcba,
+}
+
+// NOTE: every reordering is its own inverse so this converts both from device
+// order to ABCD when decoding and from ABCD to device order when encoding
+pub(crate) fn reorder_numeric_words<TIntoIterator>(
+  data: TIntoIterator,
+  endianness: Endianness,
+) -> Vec<u16>
+where
+  TIntoIterator: IntoIterator<Item = u16>,
+{
+  let mut words = data.into_iter().collect::<Vec<_>>();
+  if matches!(endianness, Endianness::Cdab | Endianness::Dcba) {
+    words.reverse();
+  }
+  if matches!(endianness, Endianness::Badc | Endianness::Dcba) {
+    words = words.into_iter().map(u16::swap_bytes).collect();
+  }
+  words
+}
+
+pub(crate) fn decode_bcd_words<TIntoIterator>(
+  data: TIntoIterator,
+) -> Option<u64>
+where
+  TIntoIterator: IntoIterator<Item = u16>,
+{
+  data.into_iter().try_fold(0u64, |acc, word| {
+    [12u16, 8, 4, 0].into_iter().try_fold(acc, |acc, shift| {
+      let digit = u64::from((word >> shift) & 0x0F);
+      if digit > 9 {
+        return None;
+      }
+      acc.checked_mul(10)?.checked_add(digit)
+    })
+  })
+}
+
+pub(crate) fn encode_bcd_words(value: u64, length: u16) -> Vec<u16> {
+  let mut remaining = value;
+  let mut words = (0..length)
+    .map(|_| {
+      [0u16, 4, 8, 12].into_iter().fold(0u16, |word, shift| {
+        let digit = (remaining % 10) as u16;
+        remaining /= 10;
+        word | (digit << shift)
+      })
+    })
+    .collect::<Vec<_>>();
+  words.reverse();
+  words
+}
+
+pub(crate) fn decode_sign_magnitude_words<TIntoIterator>(
+  data: TIntoIterator,
+) -> i64
+where
+  TIntoIterator: IntoIterator<Item = u16>,
+{
+  let words = data.into_iter().collect::<Vec<_>>();
+  let negative = words.first().is_some_and(|word| word & 0x8000 != 0);
+  let magnitude = words.iter().enumerate().fold(0u64, |acc, (index, word)| {
+    let word = if index == 0 { word & 0x7FFF } else { *word };
+    acc.checked_shl(16).unwrap_or(0) | u64::from(word)
+  });
+  let magnitude = i64::try_from(magnitude).unwrap_or(i64::MAX);
+  if negative {
+    magnitude.saturating_neg()
+  } else {
+    magnitude
+  }
+}
+
+pub(crate) fn encode_sign_magnitude_words(value: i64, length: u16) -> Vec<u16> {
+  let magnitude = value.unsigned_abs();
+  let mut words = (0..length)
+    .rev()
+    .map(|index| {
+      magnitude
+        .checked_shr(u32::from(index).saturating_mul(16))
+        .unwrap_or(0) as u16
+    })
+    .collect::<Vec<_>>();
+  if let Some(first) = words.first_mut() {
+    *first &= 0x7FFF;
+    if value < 0 {
+      *first |= 0x8000;
+    }
+  }
+  words
+}
+
 #[cfg(target_endian = "little")]
 pub(crate) fn decode_numeric_bytes<TIterator, TIntoIterator>(
   data: TIntoIterator,
@@ -50,29 +148,27 @@ where
     .collect()
 }
 
-#[cfg(target_endian = "little")]
-pub(crate) fn encode_numeric_bytes<TIterator, TIntoIterator>(
+// NOTE: takes big endian bytes so the words come out in ABCD order
+pub(crate) fn encode_numeric_bytes<TIntoIterator>(
   data: TIntoIterator,
 ) -> Vec<u16>
 where
-  TIterator: DoubleEndedIterator<Item = u8>,
-  TIntoIterator: IntoIterator<Item = u8, IntoIter = TIterator>,
+  TIntoIterator: IntoIterator<Item = u8>,
 {
   data
     .into_iter()
-    .rev()
     .chunks(2)
     .into_iter()
     .map(|mut chunk| {
       let first = chunk.next().unwrap_or(0u8);
       let second = chunk.next().unwrap_or(0u8);
-      u16::from_le_bytes([first, second])
+      u16::from_be_bytes([first, second])
     })
     .collect()
 }
 
-#[cfg(target_endian = "big")]
-pub(crate) fn encode_numeric_bytes<TIntoIterator>(
+#[cfg(target_endian = "little")]
+pub(crate) fn encode_string_bytes<TIntoIterator>(
   data: TIntoIterator,
 ) -> Vec<u16>
 where
@@ -85,12 +181,12 @@ where
     .map(|mut chunk| {
       let first = chunk.next().unwrap_or(0u8);
       let second = chunk.next().unwrap_or(0u8);
-      u16::from_le_bytes([first, second])
+      u16::from_le_bytes([second, first])
     })
     .collect()
 }
 
-#[cfg(target_endian = "little")]
+#[cfg(target_endian = "big")]
 pub(crate) fn encode_string_bytes<TIntoIterator>(
   data: TIntoIterator,
 ) -> Vec<u16>
@@ -104,26 +200,228 @@ where
     .map(|mut chunk| {
       let first = chunk.next().unwrap_or(0u8);
       let second = chunk.next().unwrap_or(0u8);
-      u16::from_le_bytes([second, first])
+      u16::from_le_bytes([first, second])
     })
     .collect()
 }
 
-#[cfg(target_endian = "big")]
-pub(crate) fn encode_string_bytes<TIntoIterator>(
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DatetimeFormat {
+  Unix,
+  Iec870,
+  Bcd,
+}
+
+pub(crate) fn decode_datetime<TIntoIterator>(
   data: TIntoIterator,
-) -> Vec<u16>
+  format: DatetimeFormat,
+  timezone: chrono_tz::Tz,
+) -> Option<chrono::DateTime<chrono::Utc>>
 where
-  TIntoIterator: IntoIterator<Item = u8>,
+  TIntoIterator: IntoIterator<Item = u16>,
 {
-  data
+  let bytes = data
     .into_iter()
+    .flat_map(|value| [(value >> 8) as u8, (value & 0xFF) as u8])
+    .collect::<Vec<_>>();
+
+  match format {
+    DatetimeFormat::Unix => {
+      let seconds = bytes
+        .iter()
+        .fold(0i64, |acc, byte| (acc << 8) | i64::from(*byte));
+      chrono::DateTime::from_timestamp(seconds, 0)
+    }
+    DatetimeFormat::Iec870 => {
+      let [millis_low, millis_high, minute, hour, day, month, year, ..] =
+        bytes.as_slice()
+      else {
+        return None;
+      };
+      if minute & 0x80 != 0 {
+        return None;
+      }
+      let millis = u32::from(*millis_low) | (u32::from(*millis_high) << 8);
+      let date = chrono::NaiveDate::from_ymd_opt(
+        i32::from(year & 0x7F).checked_add(2000)?,
+        u32::from(month & 0x0F),
+        u32::from(day & 0x1F),
+      )?;
+      let time = chrono::NaiveTime::from_hms_milli_opt(
+        u32::from(hour & 0x1F),
+        u32::from(minute & 0x3F),
+        millis.checked_div(1000)?,
+        millis.checked_rem(1000)?,
+      )?;
+      to_utc(date.and_time(time), timezone)
+    }
+    DatetimeFormat::Bcd => {
+      let [year, month, day, hour, minute, second, ..] = bytes.as_slice()
+      else {
+        return None;
+      };
+      let date = chrono::NaiveDate::from_ymd_opt(
+        i32::try_from(decode_bcd_byte(*year)?)
+          .ok()?
+          .checked_add(2000)?,
+        decode_bcd_byte(*month)?,
+        decode_bcd_byte(*day)?,
+      )?;
+      let time = chrono::NaiveTime::from_hms_opt(
+        decode_bcd_byte(*hour)?,
+        decode_bcd_byte(*minute)?,
+        decode_bcd_byte(*second)?,
+      )?;
+      to_utc(date.and_time(time), timezone)
+    }
+  }
+}
+
+pub(crate) fn encode_datetime(
+  value: chrono::DateTime<chrono::Utc>,
+  format: DatetimeFormat,
+  timezone: chrono_tz::Tz,
+) -> Vec<u16> {
+  use chrono::{Datelike, Timelike};
+
+  let local = value.with_timezone(&timezone).naive_local();
+  let year = u8::try_from(local.year().saturating_sub(2000)).unwrap_or(0);
+  let bytes = match format {
+    DatetimeFormat::Unix => {
+      let seconds = u32::try_from(value.timestamp()).unwrap_or(0);
+      seconds.to_be_bytes().to_vec()
+    }
+    DatetimeFormat::Iec870 => {
+      let millis = local
+        .second()
+        .saturating_mul(1000)
+        .saturating_add(local.nanosecond() / 1_000_000);
+      let weekday = local.weekday().number_from_monday() as u8;
+      vec![
+        (millis & 0xFF) as u8,
+        (millis >> 8) as u8,
+        local.minute() as u8,
+        local.hour() as u8,
+        (local.day() as u8) | (weekday << 5),
+        local.month() as u8,
+        year & 0x7F,
+        0,
+      ]
+    }
+    DatetimeFormat::Bcd => vec![
+      encode_bcd_byte(u32::from(year)),
+      encode_bcd_byte(local.month()),
+      encode_bcd_byte(local.day()),
+      encode_bcd_byte(local.hour()),
+      encode_bcd_byte(local.minute()),
+      encode_bcd_byte(local.second()),
+    ],
+  };
+
+  bytes
     .chunks(2)
-    .into_iter()
-    .map(|mut chunk| {
-      let first = chunk.next().unwrap_or(0u8);
-      let second = chunk.next().unwrap_or(0u8);
-      u16::from_le_bytes([first, second])
+    .map(|chunk| {
+      let first = chunk.first().copied().unwrap_or(0u8);
+      let second = chunk.get(1).copied().unwrap_or(0u8);
+      u16::from_be_bytes([first, second])
     })
     .collect()
 }
+
+fn to_utc(
+  local: chrono::NaiveDateTime,
+  timezone: chrono_tz::Tz,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+  local
+    .and_local_timezone(timezone)
+    .earliest()
+    .map(|value| value.with_timezone(&chrono::Utc))
+}
+
+fn decode_bcd_byte(byte: u8) -> Option<u32> {
+  let high = u32::from(byte >> 4);
+  let low = u32::from(byte & 0x0F);
+  if high > 9 || low > 9 {
+    return None;
+  }
+  high.checked_mul(10)?.checked_add(low)
+}
+
+fn encode_bcd_byte(value: u32) -> u8 {
+  let tens = (value / 10 % 10) as u8;
+  let ones = (value % 10) as u8;
+  (tens << 4) | ones
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bcd_words_round_trip() {
+    for (value, length) in [(0u64, 1u16), (9_999, 1), (12_345_678, 2)] {
+      let words = encode_bcd_words(value, length);
+      assert_eq!(words.len(), usize::from(length));
+      assert_eq!(decode_bcd_words(words), Some(value));
+    }
+    assert_eq!(encode_bcd_words(1_234, 1), vec![0x1234]);
+    assert_eq!(
+      encode_bcd_words(9_876_543_210_123_456, 4),
+      vec![0x9876, 0x5432, 0x1012, 0x3456]
+    );
+  }
+
+  #[test]
+  fn bcd_words_reject_invalid_digits() {
+    assert_eq!(decode_bcd_words([0x12A4]), None);
+  }
+
+  #[test]
+  fn sign_magnitude_words_round_trip() {
+    for (value, length) in [
+      (0i64, 1u16),
+      (32_767, 1),
+      (-32_767, 1),
+      (-123_456, 2),
+      (i64::MAX, 4),
+      (-i64::MAX, 4),
+    ] {
+      let words = encode_sign_magnitude_words(value, length);
+      assert_eq!(words.len(), usize::from(length));
+      assert_eq!(decode_sign_magnitude_words(words), value);
+    }
+    assert_eq!(encode_sign_magnitude_words(-1, 1), vec![0x8001]);
+    assert_eq!(encode_sign_magnitude_words(-1, 2), vec![0x8000, 0x0001]);
+  }
+
+  #[test]
+  fn datetime_round_trip() {
+    let timezone = chrono_tz::Europe::Zagreb;
+    let value = chrono::DateTime::parse_from_rfc3339("2026-03-14T15:09:26Z")
+      .unwrap()
+      .with_timezone(&chrono::Utc);
+    let millis = value
+      .checked_add_signed(chrono::TimeDelta::milliseconds(535))
+      .unwrap();
+
+    for (format, value) in [
+      (DatetimeFormat::Unix, value),
+      (DatetimeFormat::Iec870, millis),
+      (DatetimeFormat::Bcd, value),
+    ] {
+      let words = encode_datetime(value, format, timezone);
+      assert_eq!(decode_datetime(words, format, timezone), Some(value));
+    }
+  }
+
+  #[test]
+  fn datetime_encodes_local_time() {
+    let value = chrono::DateTime::parse_from_rfc3339("2026-03-14T15:09:26Z")
+      .unwrap()
+      .with_timezone(&chrono::Utc);
+    let words =
+      encode_datetime(value, DatetimeFormat::Bcd, chrono_tz::Europe::Zagreb);
+    assert_eq!(words, vec![0x2603, 0x1416, 0x0926]);
+  }
+}
@@ -0,0 +1,211 @@
+use either::Either;
+use regex::Regex;
+
+// NOTE: read device identification is a MEI transport request
+pub(crate) const READ_DEVICE_IDENTIFICATION: u8 = 0x2B;
+pub(crate) const MEI_TYPE: u8 = 0x0E;
+pub(crate) const BASIC_IDENTIFICATION: u8 = 0x01;
+
+const VENDOR_NAME_OBJECT: u8 = 0x00;
+const PRODUCT_CODE_OBJECT: u8 = 0x01;
+const REVISION_OBJECT: u8 = 0x02;
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Identification {
+  pub(crate) vendor: Option<String>,
+  pub(crate) product: Option<String>,
+  pub(crate) revision: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IdentificationMatch {
+  pub(crate) vendor: Option<Either<String, Regex>>,
+  pub(crate) product: Option<Either<String, Regex>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IdentificationPage {
+  pub(crate) objects: Vec<(u8, String)>,
+  pub(crate) next: Option<u8>,
+}
+
+impl Identification {
+  pub(crate) fn extend(&mut self, objects: Vec<(u8, String)>) {
+    for (id, value) in objects {
+      match id {
+        VENDOR_NAME_OBJECT => self.vendor = Some(value),
+        PRODUCT_CODE_OBJECT => self.product = Some(value),
+        REVISION_OBJECT => self.revision = Some(value),
+        _ => {}
+      }
+    }
+  }
+}
+
+impl IdentificationMatch {
+  pub(crate) fn matches(&self, identification: &Identification) -> bool {
+    if self.vendor.is_none() && self.product.is_none() {
+      return false;
+    }
+
+    matches_field(&self.vendor, &identification.vendor)
+      && matches_field(&self.product, &identification.product)
+  }
+}
+
+fn matches_field(
+  r#match: &Option<Either<String, Regex>>,
+  value: &Option<String>,
+) -> bool {
+  match (r#match, value) {
+    (None, _) => true,
+    (Some(_), None) => false,
+    (Some(Either::Left(string)), Some(value)) => string.eq(value),
+    (Some(Either::Right(regex)), Some(value)) => regex.is_match(value),
+  }
+}
+
+pub(crate) fn make_identification_request(object: u8) -> Vec<u8> {
+  vec![MEI_TYPE, BASIC_IDENTIFICATION, object]
+}
+
+// NOTE: data starts after the function code
+pub(crate) fn parse_identification_response(
+  data: &[u8],
+) -> Option<IdentificationPage> {
+  let mut iter = data.iter().copied();
+  if iter.next()? != MEI_TYPE {
+    return None;
+  }
+  let _code = iter.next()?;
+  let _conformity = iter.next()?;
+  let more = iter.next()? != 0x00;
+  let next = iter.next()?;
+  let count = iter.next()?;
+
+  let mut objects = Vec::with_capacity(count as usize);
+  for _ in 0..count {
+    let id = iter.next()?;
+    let length = iter.next()?;
+    let value = iter.by_ref().take(length as usize).collect::<Vec<_>>();
+    if value.len() != length as usize {
+      return None;
+    }
+    objects.push((id, String::from_utf8_lossy(&value).trim().to_string()));
+  }
+
+  Some(IdentificationPage {
+    objects,
+    next: more.then_some(next),
+  })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  fn object(id: u8, value: &str) -> Vec<u8> {
+    let mut object = vec![id, value.len() as u8];
+    object.extend_from_slice(value.as_bytes());
+    object
+  }
+
+  fn response(more: u8, next: u8, objects: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = vec![
+      MEI_TYPE,
+      BASIC_IDENTIFICATION,
+      0x81,
+      more,
+      next,
+      objects.len() as u8,
+    ];
+    for object in objects {
+      data.extend_from_slice(object);
+    }
+    data
+  }
+
+  #[test]
+  fn multi_object_pages_parse_with_paging() {
+    let data = response(
+      0xFF,
+      REVISION_OBJECT,
+      &[
+        object(VENDOR_NAME_OBJECT, "Acme "),
+        object(PRODUCT_CODE_OBJECT, "Meter-3"),
+      ],
+    );
+    let page = parse_identification_response(&data).unwrap();
+    assert_eq!(page.next, Some(REVISION_OBJECT));
+    assert_eq!(
+      page.objects,
+      vec![
+        (VENDOR_NAME_OBJECT, "Acme".to_string()),
+        (PRODUCT_CODE_OBJECT, "Meter-3".to_string()),
+      ]
+    );
+
+    let data = response(0x00, 0x00, &[object(REVISION_OBJECT, "1.2")]);
+    let last = parse_identification_response(&data).unwrap();
+    assert_eq!(last.next, None);
+
+    let mut identification = Identification::default();
+    identification.extend(page.objects);
+    identification.extend(last.objects);
+    assert_eq!(identification.vendor.as_deref(), Some("Acme"));
+    assert_eq!(identification.product.as_deref(), Some("Meter-3"));
+    assert_eq!(identification.revision.as_deref(), Some("1.2"));
+  }
+
+  #[test]
+  fn truncated_objects_fail_to_parse() {
+    let mut data = response(0x00, 0x00, &[object(VENDOR_NAME_OBJECT, "Acme")]);
+    data.pop();
+    assert!(parse_identification_response(&data).is_none());
+
+    // NOTE: object count promises more objects than were sent
+    let mut data = response(0x00, 0x00, &[object(VENDOR_NAME_OBJECT, "Acme")]);
+    data[5] = 2;
+    assert!(parse_identification_response(&data).is_none());
+
+    assert!(parse_identification_response(&data[..4]).is_none());
+  }
+
+  #[test]
+  fn non_mei_replies_fail_to_parse() {
+    let mut data = response(0x00, 0x00, &[object(VENDOR_NAME_OBJECT, "Acme")]);
+    data[0] = 0x0D;
+    assert!(parse_identification_response(&data).is_none());
+    assert!(parse_identification_response(&[]).is_none());
+  }
+
+  #[test]
+  fn matches_need_every_configured_field() {
+    let identification = Identification {
+      vendor: Some("Acme".to_string()),
+      product: Some("Meter-3".to_string()),
+      revision: None,
+    };
+    let r#match = |vendor: Option<Either<String, Regex>>,
+                   product: Option<Either<String, Regex>>| {
+      IdentificationMatch { vendor, product }
+    };
+
+    assert!(r#match(Some(Either::Left("Acme".to_string())), None)
+      .matches(&identification));
+    assert!(r#match(
+      Some(Either::Left("Acme".to_string())),
+      Some(Either::Right(Regex::new("^Meter-\\d$").unwrap())),
+    )
+    .matches(&identification));
+    assert!(!r#match(
+      Some(Either::Left("Acme".to_string())),
+      Some(Either::Left("Meter-4".to_string())),
+    )
+    .matches(&identification));
+    assert!(!r#match(None, None).matches(&identification));
+    assert!(!r#match(Some(Either::Left("Acme".to_string())), None)
+      .matches(&Identification::default()));
+  }
+}
@@ -0,0 +1,798 @@
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_modbus::{
+  client::Client, slave::SlaveContext, Request, Response, Slave,
+};
+
+use super::connection::Exception;
+
+// NOTE: modbus tcp client that matches responses by transaction id so late
+// responses to timed out requests get discarded instead of poisoning the
+// socket - tokio-modbus fails the request on any header mismatch
+
+// NOTE: stale responses read while waiting for a response before giving up
+// and letting the connection reconnect
+const MAX_STALE_RESPONSES: usize = 8;
+
+// NOTE: mbap length covers the unit id and the pdu
+const MAX_MBAP_LENGTH: u16 = 254;
+
+const HEADER_LENGTH: usize = 7;
+
+#[derive(Debug)]
+pub(crate) struct ExceptionResponse {
+  pub(crate) function: u8,
+  pub(crate) exception: Exception,
+}
+
+impl std::fmt::Display for ExceptionResponse {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "Function {:#04x} responded with {:?}",
+      self.function, self.exception
+    )
+  }
+}
+
+impl std::error::Error for ExceptionResponse {}
+
+#[derive(Debug)]
+pub(crate) struct MbapClient {
+  stream: TcpStream,
+  slave: Slave,
+  transaction: u16,
+  drains: Arc<AtomicU64>,
+  // NOTE: bytes received but not yet framed so a request that times out
+  // halfway through a frame leaves the rest for the next request to skip
+  buffer: Vec<u8>,
+}
+
+pub(crate) struct Frame {
+  pub(crate) transaction: u16,
+  pub(crate) unit: u8,
+  pub(crate) pdu: Vec<u8>,
+}
+
+impl MbapClient {
+  pub(crate) fn new(stream: TcpStream, drains: Arc<AtomicU64>) -> Self {
+    Self {
+      stream,
+      slave: Slave::tcp_device(),
+      transaction: 0,
+      drains,
+      buffer: Vec::new(),
+    }
+  }
+
+  // NOTE: anything readable before sending is a response nobody waits for
+  fn drain(&mut self) -> Result<(), Error> {
+    loop {
+      match self.stream.try_read_buf(&mut self.buffer) {
+        Ok(0) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+        Ok(_) => {}
+        Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+        Err(error) => return Err(error),
+      }
+    }
+
+    while take_frame(&mut self.buffer)?.is_some() {
+      tracing::debug!("Drained stale response before request");
+      self.drains.fetch_add(1, Ordering::Relaxed);
+    }
+
+    Ok(())
+  }
+
+  // NOTE: read_buf is cancel safe unlike read_exact
+  async fn receive(&mut self) -> Result<Frame, Error> {
+    loop {
+      if let Some(frame) = take_frame(&mut self.buffer)? {
+        return Ok(frame);
+      }
+
+      if self.stream.read_buf(&mut self.buffer).await? == 0 {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+      }
+    }
+  }
+}
+
+impl SlaveContext for MbapClient {
+  fn set_slave(&mut self, slave: Slave) {
+    self.slave = slave;
+  }
+}
+
+#[async_trait::async_trait]
+impl Client for MbapClient {
+  async fn call(&mut self, request: Request<'_>) -> Result<Response, Error> {
+    if request == Request::Disconnect {
+      self.stream.shutdown().await?;
+      return Err(Error::from(ErrorKind::NotConnected));
+    }
+
+    self.drain()?;
+
+    let function = function_code(&request);
+    let pdu = encode_request(&request)?;
+    self.transaction = self.transaction.wrapping_add(1);
+    let adu = encode_frame(self.transaction, self.slave.0, &pdu)?;
+    self.stream.write_all(&adu).await?;
+
+    for _ in 0..MAX_STALE_RESPONSES {
+      let frame = self.receive().await?;
+      if frame.transaction != self.transaction || frame.unit != self.slave.0 {
+        tracing::debug!(
+          "Discarded stale response {} from {} while expecting {} from {}",
+          frame.transaction,
+          frame.unit,
+          self.transaction,
+          self.slave.0
+        );
+        self.drains.fetch_add(1, Ordering::Relaxed);
+        continue;
+      }
+
+      return decode_response(function, &request, &frame.pdu);
+    }
+
+    Err(Error::new(
+      ErrorKind::InvalidData,
+      "Failed draining stale responses",
+    ))
+  }
+}
+
+pub(crate) async fn read_frame<TRead: AsyncRead + Unpin>(
+  stream: &mut TRead,
+) -> Result<Frame, Error> {
+  let mut header = [0u8; HEADER_LENGTH];
+  stream.read_exact(&mut header).await?;
+  let length = frame_length(&header)?;
+
+  let mut pdu = vec![0u8; length.saturating_sub(1)];
+  stream.read_exact(&mut pdu).await?;
+
+  let [t0, t1, _, _, _, _, unit] = header;
+  Ok(Frame {
+    transaction: u16::from_be_bytes([t0, t1]),
+    unit,
+    pdu,
+  })
+}
+
+// NOTE: removes the first frame from the buffer once it is complete
+fn take_frame(buffer: &mut Vec<u8>) -> Result<Option<Frame>, Error> {
+  let Some(header) = buffer.get(..HEADER_LENGTH) else {
+    return Ok(None);
+  };
+  let length = frame_length(header)?;
+  let end = HEADER_LENGTH.saturating_add(length.saturating_sub(1));
+  if buffer.len() < end {
+    return Ok(None);
+  }
+
+  let frame: Vec<u8> = buffer.drain(..end).collect();
+  let (header, pdu) = frame.split_at(HEADER_LENGTH);
+  Ok(Some(Frame {
+    transaction: u16::from_be_bytes([header[0], header[1]]),
+    unit: header[6],
+    pdu: pdu.to_vec(),
+  }))
+}
+
+fn frame_length(header: &[u8]) -> Result<usize, Error> {
+  let [_, _, p0, p1, l0, l1, _] = header else {
+    return Err(invalid("Invalid frame header"));
+  };
+  let protocol = u16::from_be_bytes([*p0, *p1]);
+  let length = u16::from_be_bytes([*l0, *l1]);
+  if protocol != 0 || !(2..=MAX_MBAP_LENGTH).contains(&length) {
+    return Err(Error::new(
+      ErrorKind::InvalidData,
+      format!(
+        "Invalid frame with protocol {} and length {}",
+        protocol, length
+      ),
+    ));
+  }
+
+  Ok(usize::from(length))
+}
+
+pub(crate) fn encode_frame(
+  transaction: u16,
+  unit: u8,
+  pdu: &[u8],
+) -> Result<Vec<u8>, Error> {
+  let length = u16::try_from(pdu.len().saturating_add(1))
+    .ok()
+    .filter(|length| *length <= MAX_MBAP_LENGTH)
+    .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Pdu too long"))?;
+
+  let mut adu = Vec::with_capacity(HEADER_LENGTH.saturating_add(pdu.len()));
+  adu.extend_from_slice(&transaction.to_be_bytes());
+  adu.extend_from_slice(&0u16.to_be_bytes());
+  adu.extend_from_slice(&length.to_be_bytes());
+  adu.push(unit);
+  adu.extend_from_slice(pdu);
+
+  Ok(adu)
+}
+
+fn function_code(request: &Request<'_>) -> u8 {
+  match request {
+    Request::ReadCoils(_, _) => 0x01,
+    Request::ReadDiscreteInputs(_, _) => 0x02,
+    Request::ReadHoldingRegisters(_, _) => 0x03,
+    Request::ReadInputRegisters(_, _) => 0x04,
+    Request::WriteSingleCoil(_, _) => 0x05,
+    Request::WriteSingleRegister(_, _) => 0x06,
+    Request::WriteMultipleCoils(_, _) => 0x0F,
+    Request::WriteMultipleRegisters(_, _) => 0x10,
+    Request::MaskWriteRegister(_, _, _) => 0x16,
+    Request::ReadWriteMultipleRegisters(_, _, _, _) => 0x17,
+    Request::Custom(function, _) => *function,
+    Request::Disconnect => 0x00,
+  }
+}
+
+fn encode_request(request: &Request<'_>) -> Result<Vec<u8>, Error> {
+  let mut pdu = vec![function_code(request)];
+  match request {
+    Request::ReadCoils(address, quantity)
+    | Request::ReadDiscreteInputs(address, quantity)
+    | Request::ReadHoldingRegisters(address, quantity)
+    | Request::ReadInputRegisters(address, quantity) => {
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(&quantity.to_be_bytes());
+    }
+    Request::WriteSingleCoil(address, coil) => {
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(
+        &(if *coil { 0xFF00u16 } else { 0x0000 }).to_be_bytes(),
+      );
+    }
+    Request::WriteSingleRegister(address, word) => {
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(&word.to_be_bytes());
+    }
+    Request::WriteMultipleCoils(address, coils) => {
+      let bytes = pack_bits(coils);
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(&to_quantity(coils.len())?.to_be_bytes());
+      pdu.push(to_byte_count(bytes.len())?);
+      pdu.extend_from_slice(&bytes);
+    }
+    Request::WriteMultipleRegisters(address, words) => {
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(&to_quantity(words.len())?.to_be_bytes());
+      pdu.push(to_byte_count(words.len().saturating_mul(2))?);
+      for word in words.iter() {
+        pdu.extend_from_slice(&word.to_be_bytes());
+      }
+    }
+    Request::MaskWriteRegister(address, and, or) => {
+      pdu.extend_from_slice(&address.to_be_bytes());
+      pdu.extend_from_slice(&and.to_be_bytes());
+      pdu.extend_from_slice(&or.to_be_bytes());
+    }
+    Request::ReadWriteMultipleRegisters(
+      read_address,
+      read_quantity,
+      write_address,
+      words,
+    ) => {
+      pdu.extend_from_slice(&read_address.to_be_bytes());
+      pdu.extend_from_slice(&read_quantity.to_be_bytes());
+      pdu.extend_from_slice(&write_address.to_be_bytes());
+      pdu.extend_from_slice(&to_quantity(words.len())?.to_be_bytes());
+      pdu.push(to_byte_count(words.len().saturating_mul(2))?);
+      for word in words.iter() {
+        pdu.extend_from_slice(&word.to_be_bytes());
+      }
+    }
+    Request::Custom(_, data) => pdu.extend_from_slice(data),
+    Request::Disconnect => {
+      return Err(Error::from(ErrorKind::NotConnected));
+    }
+  }
+
+  Ok(pdu)
+}
+
+fn decode_response(
+  function: u8,
+  request: &Request<'_>,
+  pdu: &[u8],
+) -> Result<Response, Error> {
+  let (&code, data) = pdu.split_first().ok_or_else(|| invalid("Empty pdu"))?;
+  if code == function | 0x80 {
+    let exception = data
+      .first()
+      .and_then(|code| Exception::from_code(*code))
+      .ok_or_else(|| invalid("Unknown exception code"))?;
+    return Err(Error::other(ExceptionResponse {
+      function,
+      exception,
+    }));
+  }
+  if code != function {
+    return Err(invalid("Unexpected function code"));
+  }
+
+  let response = match request {
+    Request::ReadCoils(_, _) => {
+      Response::ReadCoils(unpack_bits(counted(data)?))
+    }
+    Request::ReadDiscreteInputs(_, _) => {
+      Response::ReadDiscreteInputs(unpack_bits(counted(data)?))
+    }
+    Request::ReadHoldingRegisters(_, _) => {
+      Response::ReadHoldingRegisters(to_words(counted(data)?)?)
+    }
+    Request::ReadInputRegisters(_, _) => {
+      Response::ReadInputRegisters(to_words(counted(data)?)?)
+    }
+    Request::ReadWriteMultipleRegisters(_, _, _, _) => {
+      Response::ReadWriteMultipleRegisters(to_words(counted(data)?)?)
+    }
+    Request::WriteSingleCoil(_, _) => {
+      let [address, value] = two_words(data)?;
+      Response::WriteSingleCoil(address, value == 0xFF00)
+    }
+    Request::WriteSingleRegister(_, _) => {
+      let [address, value] = two_words(data)?;
+      Response::WriteSingleRegister(address, value)
+    }
+    Request::WriteMultipleCoils(_, _) => {
+      let [address, quantity] = two_words(data)?;
+      Response::WriteMultipleCoils(address, quantity)
+    }
+    Request::WriteMultipleRegisters(_, _) => {
+      let [address, quantity] = two_words(data)?;
+      Response::WriteMultipleRegisters(address, quantity)
+    }
+    Request::MaskWriteRegister(_, _, _) => {
+      let words = to_words(data)?;
+      match words.as_slice() {
+        [address, and, or] => Response::MaskWriteRegister(*address, *and, *or),
+        _ => return Err(invalid("Invalid mask write response")),
+      }
+    }
+    Request::Custom(_, _) => {
+      Response::Custom(code, bytes::Bytes::copy_from_slice(data))
+    }
+    Request::Disconnect => return Err(Error::from(ErrorKind::NotConnected)),
+  };
+
+  Ok(response)
+}
+
+fn invalid(message: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn counted(data: &[u8]) -> Result<&[u8], Error> {
+  let (&count, rest) = data
+    .split_first()
+    .ok_or_else(|| invalid("Missing byte count"))?;
+  rest
+    .get(..usize::from(count))
+    .ok_or_else(|| invalid("Byte count exceeds pdu"))
+}
+
+fn to_words(data: &[u8]) -> Result<Vec<u16>, Error> {
+  if !data.len().is_multiple_of(2) {
+    return Err(invalid("Odd register byte count"));
+  }
+
+  Ok(
+    data
+      .chunks_exact(2)
+      .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+      .collect(),
+  )
+}
+
+fn two_words(data: &[u8]) -> Result<[u16; 2], Error> {
+  match to_words(data)?.as_slice() {
+    [first, second] => Ok([*first, *second]),
+    _ => Err(invalid("Invalid write response")),
+  }
+}
+
+fn to_quantity(len: usize) -> Result<u16, Error> {
+  u16::try_from(len)
+    .map_err(|_| Error::new(ErrorKind::InvalidInput, "Quantity overflow"))
+}
+
+fn to_byte_count(len: usize) -> Result<u8, Error> {
+  u8::try_from(len)
+    .map_err(|_| Error::new(ErrorKind::InvalidInput, "Byte count overflow"))
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+  bits
+    .chunks(8)
+    .map(|chunk| {
+      chunk
+        .iter()
+        .enumerate()
+        .fold(0u8, |byte, (index, bit)| byte | (u8::from(*bit) << index))
+    })
+    .collect()
+}
+
+fn unpack_bits(bytes: &[u8]) -> Vec<bool> {
+  bytes
+    .iter()
+    .flat_map(|byte| (0..8).map(move |index| (byte >> index) & 1 == 1))
+    .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  use std::borrow::Cow;
+
+  use tokio::net::TcpListener;
+
+  fn exception_of(error: &Error) -> Option<Exception> {
+    error
+      .get_ref()
+      .and_then(|error| error.downcast_ref::<ExceptionResponse>())
+      .map(|response| response.exception)
+  }
+
+  #[test]
+  fn requests_encode_to_pdus() {
+    let cases: Vec<(Request<'_>, Vec<u8>)> = vec![
+      (
+        Request::ReadCoils(0x0013, 0x0013),
+        vec![0x01, 0, 0x13, 0, 0x13],
+      ),
+      (
+        Request::ReadDiscreteInputs(0x00C4, 0x0016),
+        vec![0x02, 0, 0xC4, 0, 0x16],
+      ),
+      (
+        Request::ReadHoldingRegisters(0x006B, 0x0003),
+        vec![0x03, 0, 0x6B, 0, 0x03],
+      ),
+      (
+        Request::ReadInputRegisters(0x0008, 0x0001),
+        vec![0x04, 0, 0x08, 0, 0x01],
+      ),
+      (
+        Request::WriteSingleCoil(0x00AC, true),
+        vec![0x05, 0, 0xAC, 0xFF, 0x00],
+      ),
+      (
+        Request::WriteSingleCoil(0x00AC, false),
+        vec![0x05, 0, 0xAC, 0x00, 0x00],
+      ),
+      (
+        Request::WriteSingleRegister(0x0001, 0x0003),
+        vec![0x06, 0, 0x01, 0, 0x03],
+      ),
+      (
+        Request::WriteMultipleCoils(
+          0x0013,
+          Cow::Owned(vec![
+            true, false, true, true, false, false, true, true, true, false,
+          ]),
+        ),
+        vec![0x0F, 0, 0x13, 0, 0x0A, 0x02, 0xCD, 0x01],
+      ),
+      (
+        Request::WriteMultipleRegisters(
+          0x0001,
+          Cow::Owned(vec![0x000A, 0x0102]),
+        ),
+        vec![0x10, 0, 0x01, 0, 0x02, 0x04, 0, 0x0A, 0x01, 0x02],
+      ),
+      (
+        Request::MaskWriteRegister(0x0004, 0x00F2, 0x0025),
+        vec![0x16, 0, 0x04, 0, 0xF2, 0, 0x25],
+      ),
+      (
+        Request::ReadWriteMultipleRegisters(
+          0x0003,
+          0x0006,
+          0x000E,
+          Cow::Owned(vec![0x00FF, 0x00FF, 0x00FF]),
+        ),
+        vec![
+          0x17, 0, 0x03, 0, 0x06, 0, 0x0E, 0, 0x03, 0x06, 0, 0xFF, 0, 0xFF, 0,
+          0xFF,
+        ],
+      ),
+      (
+        Request::Custom(0x2B, Cow::Owned(vec![0x0E, 0x01, 0x00])),
+        vec![0x2B, 0x0E, 0x01, 0x00],
+      ),
+    ];
+
+    for (request, expected) in cases {
+      assert_eq!(encode_request(&request).unwrap(), expected, "{request:?}");
+    }
+
+    assert!(encode_request(&Request::Disconnect).is_err());
+  }
+
+  #[test]
+  fn pdus_decode_to_responses() {
+    let cases: Vec<(Request<'_>, Vec<u8>, Response)> = vec![
+      (
+        Request::ReadCoils(0x0013, 0x000A),
+        vec![0x01, 0x02, 0xCD, 0x01],
+        Response::ReadCoils(vec![
+          true, false, true, true, false, false, true, true, true, false,
+          false, false, false, false, false, false,
+        ]),
+      ),
+      (
+        Request::ReadDiscreteInputs(0x00C4, 0x0008),
+        vec![0x02, 0x01, 0x35],
+        Response::ReadDiscreteInputs(vec![
+          true, false, true, false, true, true, false, false,
+        ]),
+      ),
+      (
+        Request::ReadHoldingRegisters(0x006B, 0x0002),
+        vec![0x03, 0x04, 0x02, 0x2B, 0x00, 0x00],
+        Response::ReadHoldingRegisters(vec![0x022B, 0x0000]),
+      ),
+      (
+        Request::ReadInputRegisters(0x0008, 0x0001),
+        vec![0x04, 0x02, 0x00, 0x0A],
+        Response::ReadInputRegisters(vec![0x000A]),
+      ),
+      (
+        Request::WriteSingleCoil(0x00AC, true),
+        vec![0x05, 0, 0xAC, 0xFF, 0x00],
+        Response::WriteSingleCoil(0x00AC, true),
+      ),
+      (
+        Request::WriteSingleRegister(0x0001, 0x0003),
+        vec![0x06, 0, 0x01, 0, 0x03],
+        Response::WriteSingleRegister(0x0001, 0x0003),
+      ),
+      (
+        Request::WriteMultipleCoils(0x0013, Cow::Owned(vec![true; 10])),
+        vec![0x0F, 0, 0x13, 0, 0x0A],
+        Response::WriteMultipleCoils(0x0013, 0x000A),
+      ),
+      (
+        Request::WriteMultipleRegisters(0x0001, Cow::Owned(vec![1, 2])),
+        vec![0x10, 0, 0x01, 0, 0x02],
+        Response::WriteMultipleRegisters(0x0001, 0x0002),
+      ),
+      (
+        Request::MaskWriteRegister(0x0004, 0x00F2, 0x0025),
+        vec![0x16, 0, 0x04, 0, 0xF2, 0, 0x25],
+        Response::MaskWriteRegister(0x0004, 0x00F2, 0x0025),
+      ),
+      (
+        Request::ReadWriteMultipleRegisters(
+          0x0003,
+          0x0002,
+          0x000E,
+          Cow::Owned(vec![0x00FF]),
+        ),
+        vec![0x17, 0x04, 0x00, 0xFE, 0x0A, 0xCD],
+        Response::ReadWriteMultipleRegisters(vec![0x00FE, 0x0ACD]),
+      ),
+      (
+        Request::Custom(0x2B, Cow::Owned(vec![0x0E])),
+        vec![0x2B, 0x0E, 0x01],
+        Response::Custom(0x2B, bytes::Bytes::from_static(&[0x0E, 0x01])),
+      ),
+    ];
+
+    for (request, pdu, expected) in cases {
+      let function = function_code(&request);
+      assert_eq!(
+        decode_response(function, &request, &pdu).unwrap(),
+        expected,
+        "{request:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn exception_frames_decode_to_exceptions() {
+    let request = Request::ReadHoldingRegisters(0x0000, 0x0001);
+
+    let error = decode_response(0x03, &request, &[0x83, 0x02]).unwrap_err();
+    assert_eq!(exception_of(&error), Some(Exception::IllegalDataAddress));
+
+    let error = decode_response(0x03, &request, &[0x83, 0x0B]).unwrap_err();
+    assert_eq!(exception_of(&error), Some(Exception::GatewayTargetDevice));
+
+    let error = decode_response(0x03, &request, &[0x83, 0x7F]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(exception_of(&error), None);
+
+    let error = decode_response(0x03, &request, &[0x83]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let error = decode_response(0x03, &request, &[0x84, 0x02]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(exception_of(&error), None);
+  }
+
+  #[test]
+  fn malformed_pdus_fail_to_decode() {
+    let request = Request::ReadHoldingRegisters(0x0000, 0x0002);
+    let malformed: Vec<&[u8]> = vec![
+      &[],
+      &[0x03],
+      &[0x03, 0x04, 0x00, 0x01],
+      &[0x03, 0x03, 0x00, 0x01, 0x02],
+    ];
+    for pdu in malformed {
+      let error = decode_response(0x03, &request, pdu).unwrap_err();
+      assert_eq!(error.kind(), ErrorKind::InvalidData, "{pdu:?}");
+    }
+
+    let request = Request::WriteSingleRegister(0x0001, 0x0003);
+    let error =
+      decode_response(0x06, &request, &[0x06, 0x00, 0x01]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let request = Request::MaskWriteRegister(0x0004, 0x00F2, 0x0025);
+    let error =
+      decode_response(0x16, &request, &[0x16, 0x00, 0x04, 0x00, 0xF2])
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+  }
+
+  #[tokio::test]
+  async fn malformed_lengths_fail_to_read() {
+    let frames: Vec<&[u8]> = vec![
+      &[0, 1, 0, 0, 0, 0, 1],
+      &[0, 1, 0, 0, 0, 1, 1],
+      &[0, 1, 0, 0, 0, 255, 1],
+      &[0, 1, 0, 1, 0, 3, 1, 0x03, 0x00],
+    ];
+    for frame in frames {
+      let mut stream = frame;
+      let error = read_frame(&mut stream).await.err().unwrap();
+      assert_eq!(error.kind(), ErrorKind::InvalidData, "{frame:?}");
+
+      let mut buffer = frame.to_vec();
+      let error = take_frame(&mut buffer).err().unwrap();
+      assert_eq!(error.kind(), ErrorKind::InvalidData, "{frame:?}");
+    }
+
+    let mut stream: &[u8] = &[0, 1, 0, 0, 0, 5, 1, 0x03, 0x02];
+    let error = read_frame(&mut stream).await.err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+  }
+
+  #[test]
+  fn frames_encode_and_take_back() {
+    let pdu = [0x03, 0x02, 0x00, 0x2A];
+    let adu = encode_frame(0x1234, 0x11, &pdu).unwrap();
+    assert_eq!(adu, vec![0x12, 0x34, 0, 0, 0, 5, 0x11, 0x03, 0x02, 0, 0x2A]);
+
+    let mut buffer = adu[..8].to_vec();
+    assert!(take_frame(&mut buffer).unwrap().is_none());
+    assert_eq!(buffer.len(), 8);
+
+    buffer.extend_from_slice(&adu[8..]);
+    buffer.extend_from_slice(&adu[..3]);
+    let frame = take_frame(&mut buffer).unwrap().unwrap();
+    assert_eq!(frame.transaction, 0x1234);
+    assert_eq!(frame.unit, 0x11);
+    assert_eq!(frame.pdu, pdu);
+    assert_eq!(buffer, adu[..3]);
+
+    assert!(encode_frame(1, 1, &[0u8; 254]).is_err());
+  }
+
+  #[tokio::test]
+  async fn stale_transactions_are_skipped() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let request = read_frame(&mut stream).await.unwrap();
+      let stale = encode_frame(
+        request.transaction.wrapping_sub(1),
+        request.unit,
+        &[0x03, 0x02, 0x00, 0x01],
+      )
+      .unwrap();
+      let foreign =
+        encode_frame(request.transaction, 0x22, &[0x03, 0x02, 0x00, 0x02])
+          .unwrap();
+      let current = encode_frame(
+        request.transaction,
+        request.unit,
+        &[0x03, 0x02, 0x00, 0x2A],
+      )
+      .unwrap();
+      // NOTE: split the stale frame to exercise partial reads
+      let (head, tail) = stale.split_at(4);
+      stream.write_all(head).await.unwrap();
+      stream.flush().await.unwrap();
+      tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+      stream.write_all(tail).await.unwrap();
+      stream.write_all(&foreign).await.unwrap();
+      stream.write_all(&current).await.unwrap();
+      stream
+    });
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let drains = Arc::new(AtomicU64::new(0));
+    let mut client = MbapClient::new(stream, drains.clone());
+    client.set_slave(Slave(0x11));
+    let response = client
+      .call(Request::ReadHoldingRegisters(0x0000, 0x0001))
+      .await
+      .unwrap();
+
+    assert_eq!(response, Response::ReadHoldingRegisters(vec![0x002A]));
+    assert_eq!(drains.load(Ordering::Relaxed), 2);
+    drop(server.await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn timed_out_frames_are_drained_before_the_next_request() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let first = read_frame(&mut stream).await.unwrap();
+      let late =
+        encode_frame(first.transaction, first.unit, &[0x03, 0x02, 0x00, 0x01])
+          .unwrap();
+      let (head, tail) = late.split_at(5);
+      stream.write_all(head).await.unwrap();
+      stream.flush().await.unwrap();
+      tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+      stream.write_all(tail).await.unwrap();
+      stream.flush().await.unwrap();
+
+      let second = read_frame(&mut stream).await.unwrap();
+      let current = encode_frame(
+        second.transaction,
+        second.unit,
+        &[0x03, 0x02, 0x00, 0x2A],
+      )
+      .unwrap();
+      stream.write_all(&current).await.unwrap();
+      stream
+    });
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let drains = Arc::new(AtomicU64::new(0));
+    let mut client = MbapClient::new(stream, drains.clone());
+    let timed_out = tokio::time::timeout(
+      std::time::Duration::from_millis(50),
+      client.call(Request::ReadHoldingRegisters(0x0000, 0x0001)),
+    )
+    .await;
+    assert!(timed_out.is_err());
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let response = client
+      .call(Request::ReadHoldingRegisters(0x0000, 0x0001))
+      .await
+      .unwrap();
+
+    assert_eq!(response, Response::ReadHoldingRegisters(vec![0x002A]));
+    assert_eq!(drains.load(Ordering::Relaxed), 1);
+    drop(server.await.unwrap());
+  }
+}
@@ -0,0 +1,167 @@
+use super::connection::{Destination, Transport};
+
+// NOTE: upper bounds in milliseconds with an implicit overflow bucket
+pub(crate) const LATENCY_BUCKETS: [i64; 9] =
+  [10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Histogram {
+  pub(crate) buckets: Vec<u64>,
+  pub(crate) count: u64,
+  pub(crate) sum: u64,
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Counters {
+  pub(crate) reads: u64,
+  pub(crate) read_errors: u64,
+  pub(crate) read_congestions: u64,
+  pub(crate) read_latency: Histogram,
+  pub(crate) writes: u64,
+  pub(crate) write_errors: u64,
+  pub(crate) write_latency: Histogram,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceMetrics {
+  pub(crate) id: String,
+  pub(crate) slave: Option<u8>,
+  pub(crate) counters: Counters,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GatewayMetrics {
+  pub(crate) gateway: String,
+  // NOTE: stale responses discarded on the gateway connections
+  pub(crate) drains: u64,
+  // NOTE: includes reads of destinations without a bound device like
+  // discovery probes which only count towards the gateway
+  pub(crate) counters: Counters,
+  pub(crate) devices: Vec<DeviceMetrics>,
+}
+
+impl Histogram {
+  pub(crate) fn record(&mut self, time: chrono::Duration) {
+    if self.buckets.is_empty() {
+      self.buckets = vec![0; LATENCY_BUCKETS.len().saturating_add(1)];
+    }
+
+    let milliseconds = time.num_milliseconds().max(0);
+    let bucket = LATENCY_BUCKETS
+      .iter()
+      .position(|bound| milliseconds <= *bound)
+      .unwrap_or(LATENCY_BUCKETS.len());
+    if let Some(count) = self.buckets.get_mut(bucket) {
+      *count = count.saturating_add(1);
+    }
+    self.count = self.count.saturating_add(1);
+    self.sum = self.sum.saturating_add(milliseconds as u64);
+  }
+
+  pub(crate) fn merge(&mut self, other: &Histogram) {
+    if self.buckets.len() < other.buckets.len() {
+      self.buckets.resize(other.buckets.len(), 0);
+    }
+    for (count, other) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+      *count = count.saturating_add(*other);
+    }
+    self.count = self.count.saturating_add(other.count);
+    self.sum = self.sum.saturating_add(other.sum);
+  }
+}
+
+impl Counters {
+  pub(crate) fn record_read(
+    &mut self,
+    error: bool,
+    congested: bool,
+    time: Option<chrono::Duration>,
+  ) {
+    self.reads = self.reads.saturating_add(1);
+    if error {
+      self.read_errors = self.read_errors.saturating_add(1);
+    }
+    if congested {
+      self.read_congestions = self.read_congestions.saturating_add(1);
+    }
+    if let Some(time) = time {
+      self.read_latency.record(time);
+    }
+  }
+
+  pub(crate) fn record_write(
+    &mut self,
+    error: bool,
+    time: Option<chrono::Duration>,
+  ) {
+    self.writes = self.writes.saturating_add(1);
+    if error {
+      self.write_errors = self.write_errors.saturating_add(1);
+    }
+    if let Some(time) = time {
+      self.write_latency.record(time);
+    }
+  }
+
+  pub(crate) fn merge(&mut self, other: &Counters) {
+    self.reads = self.reads.saturating_add(other.reads);
+    self.read_errors = self.read_errors.saturating_add(other.read_errors);
+    self.read_congestions =
+      self.read_congestions.saturating_add(other.read_congestions);
+    self.read_latency.merge(&other.read_latency);
+    self.writes = self.writes.saturating_add(other.writes);
+    self.write_errors = self.write_errors.saturating_add(other.write_errors);
+    self.write_latency.merge(&other.write_latency);
+  }
+}
+
+impl GatewayMetrics {
+  pub(crate) fn new<
+    TIntoIterator: IntoIterator<Item = (Destination, Option<String>, Counters)>,
+  >(
+    transport: &Transport,
+    drains: u64,
+    devices: TIntoIterator,
+  ) -> Self {
+    let mut counters = Counters::default();
+    let devices = devices
+      .into_iter()
+      .filter_map(|(destination, id, device_counters)| {
+        counters.merge(&device_counters);
+        Some(DeviceMetrics {
+          id: id?,
+          slave: destination.slave,
+          counters: device_counters,
+        })
+      })
+      .collect::<Vec<_>>();
+
+    Self {
+      gateway: match transport {
+        Transport::Tcp(address) | Transport::RtuOverTcp(address) => {
+          address.to_string()
+        }
+        Transport::Serial(serial) => serial.path.clone(),
+      },
+      drains,
+      counters,
+      devices,
+    }
+  }
+
+  // NOTE: adds counters persisted before a restart
+  pub(crate) fn merge(&mut self, other: &GatewayMetrics) {
+    self.drains = self.drains.saturating_add(other.drains);
+    self.counters.merge(&other.counters);
+    for other in other.devices.iter() {
+      match self.devices.iter_mut().find(|device| device.id == other.id) {
+        Some(device) => device.counters.merge(&other.counters),
+        None => self.devices.push(other.clone()),
+      }
+    }
+  }
+}
@@ -1,12 +1,24 @@
 pub(crate) mod batch;
+pub(crate) mod concentrator;
 pub(crate) mod connection;
 pub(crate) mod encoding;
+pub(crate) mod identification;
+pub(crate) mod mbap;
+pub(crate) mod metrics;
 pub(crate) mod record;
 pub(crate) mod register;
 pub(crate) mod service;
 pub(crate) mod span;
 pub(crate) mod worker;
 
-pub(crate) use connection::Destination;
+pub(crate) use batch::{Batching, Hole, Plan, MAX_BATCH_QUANTITY};
+pub(crate) use connection::{
+  DataBits, Destination, Exception, ExceptionClass, Framing, Parity,
+  SerialTransport, StopBits, Transport,
+};
+pub(crate) use encoding::{DatetimeFormat, Endianness};
+pub(crate) use identification::{Identification, IdentificationMatch};
+pub(crate) use metrics::GatewayMetrics;
 pub(crate) use register::*;
 pub(crate) use service::*;
+pub(crate) use span::{RegisterTable, SimpleSpan};
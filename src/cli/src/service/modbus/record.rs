@@ -1,6 +1,6 @@
 use tokio_modbus::{Address, Quantity};
 
-use super::span::Span;
+use super::span::{RegisterTable, Span};
 
 pub(crate) trait Record: Span {
   fn values(&self) -> impl Iterator<Item = u16>;
@@ -8,11 +8,16 @@ pub(crate) trait Record: Span {
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub(crate) struct SimpleRecord {
+  pub(crate) table: RegisterTable,
   pub(crate) address: u16,
   pub(crate) values: Vec<u16>,
 }
 
 impl Span for SimpleRecord {
+  fn table(&self) -> RegisterTable {
+    self.table
+  }
+
   fn address(&self) -> Address {
     self.address
   }
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::iter::IntoIterator;
+use std::sync::Arc;
 
 use either::Either;
 use regex::Regex;
@@ -11,8 +12,6 @@ use super::encoding::*;
 use super::record::*;
 use super::span::*;
 
-// TODO: make the MAX checking a config option
-
 pub(crate) trait RegisterStorage {
   fn quantity(&self) -> Quantity;
 }
@@ -22,9 +21,21 @@ pub(crate) struct StringRegisterKind {
   pub(crate) length: Quantity,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MissingValuePolicy {
+  Null,
+  Zero,
+  Drop,
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct NumericRegisterKind {
   pub(crate) multiplier: Option<Decimal>,
+  pub(crate) offset: Option<Decimal>,
+  pub(crate) endianness: Endianness,
+  // NOTE: None means the type MAX for integers and nothing for floats
+  pub(crate) sentinels: Option<Vec<Decimal>>,
+  pub(crate) missing: MissingValuePolicy,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -32,7 +43,27 @@ pub(crate) struct RawRegisterKind {
   pub(crate) length: Quantity,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct BitsRegisterKind {
+  pub(crate) length: Quantity,
+  // NOTE: shared so parsed values don't copy the names on every poll
+  pub(crate) bits: Arc<Vec<(String, u8)>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnumRegisterKind {
+  pub(crate) length: Quantity,
+  pub(crate) labels: Arc<Vec<(u64, String)>>,
+}
+
 #[derive(Debug, Clone, Copy)]
+pub(crate) struct DatetimeRegisterKind {
+  pub(crate) format: DatetimeFormat,
+  pub(crate) timezone: chrono_tz::Tz,
+  pub(crate) timestamp: bool,
+}
+
+#[derive(Debug, Clone)]
 pub(crate) enum RegisterKindStorage {
   U16(NumericRegisterKind),
   U32(NumericRegisterKind),
@@ -42,8 +73,17 @@ pub(crate) enum RegisterKindStorage {
   S64(NumericRegisterKind),
   F32(NumericRegisterKind),
   F64(NumericRegisterKind),
+  Bcd16(NumericRegisterKind),
+  Bcd32(NumericRegisterKind),
+  Bcd64(NumericRegisterKind),
+  Sm16(NumericRegisterKind),
+  Sm32(NumericRegisterKind),
+  Sm64(NumericRegisterKind),
   String(StringRegisterKind),
   Raw(RawRegisterKind),
+  Bits(BitsRegisterKind),
+  Enum(EnumRegisterKind),
+  Datetime(DatetimeRegisterKind),
 }
 
 #[derive(Debug, Clone)]
@@ -52,18 +92,60 @@ pub(crate) struct RegisterValue<T> {
   pub(crate) timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct NumericRegisterValue {
+  pub(crate) value: Option<Decimal>,
+  pub(crate) timestamp: chrono::DateTime<chrono::Utc>,
+  pub(crate) endianness: Endianness,
+  pub(crate) missing: MissingValuePolicy,
+  // NOTE: kept so scale factors apply to the value before the offset and
+  // so serialising writes the raw value back
+  pub(crate) multiplier: Option<Decimal>,
+  pub(crate) offset: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BitsRegisterValue {
+  pub(crate) value: u64,
+  pub(crate) timestamp: chrono::DateTime<chrono::Utc>,
+  pub(crate) kind: BitsRegisterKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnumRegisterValue {
+  pub(crate) value: u64,
+  pub(crate) timestamp: chrono::DateTime<chrono::Utc>,
+  pub(crate) kind: EnumRegisterKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DatetimeRegisterValue {
+  pub(crate) value: Option<chrono::DateTime<chrono::Utc>>,
+  pub(crate) timestamp: chrono::DateTime<chrono::Utc>,
+  pub(crate) kind: DatetimeRegisterKind,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum RegisterValueStorage {
-  U16(RegisterValue<Decimal>),
-  U32(RegisterValue<Decimal>),
-  U64(RegisterValue<Decimal>),
-  S16(RegisterValue<Decimal>),
-  S32(RegisterValue<Decimal>),
-  S64(RegisterValue<Decimal>),
-  F32(RegisterValue<Decimal>),
-  F64(RegisterValue<Decimal>),
+  U16(NumericRegisterValue),
+  U32(NumericRegisterValue),
+  U64(NumericRegisterValue),
+  S16(NumericRegisterValue),
+  S32(NumericRegisterValue),
+  S64(NumericRegisterValue),
+  F32(NumericRegisterValue),
+  F64(NumericRegisterValue),
+  Bcd16(NumericRegisterValue),
+  Bcd32(NumericRegisterValue),
+  Bcd64(NumericRegisterValue),
+  Sm16(NumericRegisterValue),
+  Sm32(NumericRegisterValue),
+  Sm64(NumericRegisterValue),
   String(RegisterValue<String>),
   Raw(RegisterValue<Vec<u16>>),
+  Bits(BitsRegisterValue),
+  Enum(EnumRegisterValue),
+  Datetime(DatetimeRegisterValue),
 }
 
 impl RegisterValueStorage {
@@ -77,11 +159,124 @@ impl RegisterValueStorage {
       RegisterValueStorage::S64(storage) => storage.timestamp,
       RegisterValueStorage::F32(storage) => storage.timestamp,
       RegisterValueStorage::F64(storage) => storage.timestamp,
+      RegisterValueStorage::Bcd16(storage) => storage.timestamp,
+      RegisterValueStorage::Bcd32(storage) => storage.timestamp,
+      RegisterValueStorage::Bcd64(storage) => storage.timestamp,
+      RegisterValueStorage::Sm16(storage) => storage.timestamp,
+      RegisterValueStorage::Sm32(storage) => storage.timestamp,
+      RegisterValueStorage::Sm64(storage) => storage.timestamp,
       RegisterValueStorage::String(storage) => storage.timestamp,
       RegisterValueStorage::Raw(storage) => storage.timestamp,
+      RegisterValueStorage::Bits(storage) => storage.timestamp,
+      RegisterValueStorage::Enum(storage) => storage.timestamp,
+      RegisterValueStorage::Datetime(storage) => storage.timestamp,
+    }
+  }
+
+  pub(crate) fn numeric(&self) -> Option<&NumericRegisterValue> {
+    match self {
+      RegisterValueStorage::U16(storage)
+      | RegisterValueStorage::U32(storage)
+      | RegisterValueStorage::U64(storage)
+      | RegisterValueStorage::S16(storage)
+      | RegisterValueStorage::S32(storage)
+      | RegisterValueStorage::S64(storage)
+      | RegisterValueStorage::F32(storage)
+      | RegisterValueStorage::F64(storage)
+      | RegisterValueStorage::Bcd16(storage)
+      | RegisterValueStorage::Bcd32(storage)
+      | RegisterValueStorage::Bcd64(storage)
+      | RegisterValueStorage::Sm16(storage)
+      | RegisterValueStorage::Sm32(storage)
+      | RegisterValueStorage::Sm64(storage) => Some(storage),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn numeric_mut(&mut self) -> Option<&mut NumericRegisterValue> {
+    match self {
+      RegisterValueStorage::U16(storage)
+      | RegisterValueStorage::U32(storage)
+      | RegisterValueStorage::U64(storage)
+      | RegisterValueStorage::S16(storage)
+      | RegisterValueStorage::S32(storage)
+      | RegisterValueStorage::S64(storage)
+      | RegisterValueStorage::F32(storage)
+      | RegisterValueStorage::F64(storage)
+      | RegisterValueStorage::Bcd16(storage)
+      | RegisterValueStorage::Bcd32(storage)
+      | RegisterValueStorage::Bcd64(storage)
+      | RegisterValueStorage::Sm16(storage)
+      | RegisterValueStorage::Sm32(storage)
+      | RegisterValueStorage::Sm64(storage) => Some(storage),
+      _ => None,
+    }
+  }
+
+  // NOTE: the value of a datetime register designated as the measurement
+  // timestamp if there is one and it was read successfully
+  pub(crate) fn designated_timestamp(
+    &self,
+  ) -> Option<chrono::DateTime<chrono::Utc>> {
+    match self {
+      RegisterValueStorage::Datetime(storage) if storage.kind.timestamp => {
+        storage.value
+      }
+      _ => None,
     }
   }
 
+  pub(crate) fn is_dropped(&self) -> bool {
+    self.numeric().is_some_and(|storage| {
+      storage.value.is_none() && storage.missing == MissingValuePolicy::Drop
+    })
+  }
+
+  // NOTE: missing values and values outside of the register kind range
+  // serialise to zero words that can't be told apart from a real zero
+  pub(crate) fn is_encodable(&self) -> bool {
+    let Some(storage) = self.numeric() else {
+      return match self {
+        RegisterValueStorage::Datetime(storage) => storage.value.is_some(),
+        _ => true,
+      };
+    };
+    let Some(value) = unscale_numeric_value(storage) else {
+      return false;
+    };
+
+    let (min, max) = match self {
+      RegisterValueStorage::U16(_) => (0, i128::from(u16::MAX)),
+      RegisterValueStorage::U32(_) => (0, i128::from(u32::MAX)),
+      RegisterValueStorage::U64(_) => (0, i128::from(u64::MAX)),
+      RegisterValueStorage::S16(_) => {
+        (i128::from(i16::MIN), i128::from(i16::MAX))
+      }
+      RegisterValueStorage::S32(_) => {
+        (i128::from(i32::MIN), i128::from(i32::MAX))
+      }
+      RegisterValueStorage::S64(_) => {
+        (i128::from(i64::MIN), i128::from(i64::MAX))
+      }
+      RegisterValueStorage::Bcd16(_) => (0, 9_999),
+      RegisterValueStorage::Bcd32(_) => (0, 99_999_999),
+      RegisterValueStorage::Bcd64(_) => (0, 9_999_999_999_999_999),
+      RegisterValueStorage::Sm16(_) => (-0x7FFF, 0x7FFF),
+      RegisterValueStorage::Sm32(_) => (-0x7FFF_FFFF, 0x7FFF_FFFF),
+      RegisterValueStorage::Sm64(_) => {
+        (i128::from(i64::MIN.saturating_add(1)), i128::from(i64::MAX))
+      }
+      RegisterValueStorage::F32(_) => {
+        return TryInto::<f32>::try_into(value)
+          .is_ok_and(|value| value.is_finite());
+      }
+      _ => return true,
+    };
+
+    let value = value.trunc();
+    value >= Decimal::from(min) && value <= Decimal::from(max)
+  }
+
   pub(crate) fn serialize(&self) -> serde_json::Value {
     match self {
       RegisterValueStorage::U16(storage) => serde_json::json!(storage.value),
@@ -92,6 +287,12 @@ impl RegisterValueStorage {
       RegisterValueStorage::S64(storage) => serde_json::json!(storage.value),
       RegisterValueStorage::F32(storage) => serde_json::json!(storage.value),
       RegisterValueStorage::F64(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Bcd16(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Bcd32(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Bcd64(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Sm16(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Sm32(storage) => serde_json::json!(storage.value),
+      RegisterValueStorage::Sm64(storage) => serde_json::json!(storage.value),
       RegisterValueStorage::String(storage) => serde_json::json!(storage.value),
       RegisterValueStorage::Raw(storage) => {
         serde_json::json!(storage
@@ -100,19 +301,78 @@ impl RegisterValueStorage {
           .map(|&num| format!("0x{:04X}", num))
           .collect::<Vec<_>>())
       }
+      RegisterValueStorage::Bits(storage) => serde_json::Value::Object(
+        storage
+          .kind
+          .bits
+          .iter()
+          .map(|(name, bit)| {
+            let set = storage
+              .value
+              .checked_shr(u32::from(*bit))
+              .is_some_and(|shifted| shifted & 1 == 1);
+            (name.clone(), serde_json::Value::Bool(set))
+          })
+          .collect::<serde_json::Map<String, serde_json::Value>>(),
+      ),
+      RegisterValueStorage::Enum(storage) => match storage.label() {
+        Some(label) => serde_json::json!(label),
+        None => serde_json::json!(storage.value),
+      },
+      RegisterValueStorage::Datetime(storage) => {
+        serde_json::json!(storage.value.map(|value| value.to_rfc3339()))
+      }
     }
   }
 }
 
+impl EnumRegisterValue {
+  pub(crate) fn label(&self) -> Option<&str> {
+    self
+      .kind
+      .labels
+      .iter()
+      .find(|(code, _)| *code == self.value)
+      .map(|(_, label)| label.as_str())
+  }
+}
+
+fn decode_flag_words<TIntoIterator>(data: TIntoIterator) -> u64
+where
+  TIntoIterator: IntoIterator<Item = u16>,
+{
+  data.into_iter().fold(0u64, |acc, word| {
+    acc.checked_shl(16).unwrap_or(0) | u64::from(word)
+  })
+}
+
+fn encode_flag_words(value: u64, length: Quantity) -> Vec<u16> {
+  (0..length)
+    .rev()
+    .map(|index| {
+      value
+        .checked_shr(u32::from(index).saturating_mul(16))
+        .unwrap_or(0) as u16
+    })
+    .collect()
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct MeasurementRegister<T: RegisterStorage> {
+  pub(crate) table: RegisterTable,
   pub(crate) address: Address,
   pub(crate) storage: T,
   pub(crate) name: String,
+  // NOTE: name of the measurement register holding the SunSpec style
+  // power of ten exponent for this register
+  pub(crate) scale_factor: Option<String>,
+  // NOTE: read only to scale other registers and left out of measurements
+  pub(crate) internal: bool,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct DetectRegister<T: RegisterStorage> {
+  pub(crate) table: RegisterTable,
   pub(crate) address: Address,
   pub(crate) storage: T,
   pub(crate) r#match: Either<String, Regex>,
@@ -120,12 +380,14 @@ pub(crate) struct DetectRegister<T: RegisterStorage> {
 
 #[derive(Debug, Clone)]
 pub(crate) struct IdRegister<T: RegisterStorage> {
+  pub(crate) table: RegisterTable,
   pub(crate) address: Address,
   pub(crate) storage: T,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct ValueRegister<T: RegisterStorage> {
+  pub(crate) table: RegisterTable,
   pub(crate) address: Address,
   pub(crate) storage: T,
 }
@@ -142,6 +404,14 @@ pub(crate) fn make_id<
     .fold(format!("{kind}-"), |acc, next| acc + next.as_str())
 }
 
+fn datetime_quantity(format: DatetimeFormat) -> Quantity {
+  match format {
+    DatetimeFormat::Unix => 2,
+    DatetimeFormat::Iec870 => 4,
+    DatetimeFormat::Bcd => 3,
+  }
+}
+
 impl RegisterStorage for RegisterKindStorage {
   fn quantity(&self) -> Quantity {
     match self {
@@ -153,8 +423,19 @@ impl RegisterStorage for RegisterKindStorage {
       RegisterKindStorage::S64(_) => 4,
       RegisterKindStorage::F32(_) => 2,
       RegisterKindStorage::F64(_) => 4,
+      RegisterKindStorage::Bcd16(_) => 1,
+      RegisterKindStorage::Bcd32(_) => 2,
+      RegisterKindStorage::Bcd64(_) => 4,
+      RegisterKindStorage::Sm16(_) => 1,
+      RegisterKindStorage::Sm32(_) => 2,
+      RegisterKindStorage::Sm64(_) => 4,
       RegisterKindStorage::String(StringRegisterKind { length }) => *length,
       RegisterKindStorage::Raw(RawRegisterKind { length }) => *length,
+      RegisterKindStorage::Bits(BitsRegisterKind { length, .. }) => *length,
+      RegisterKindStorage::Enum(EnumRegisterKind { length, .. }) => *length,
+      RegisterKindStorage::Datetime(DatetimeRegisterKind {
+        format, ..
+      }) => datetime_quantity(*format),
     }
   }
 }
@@ -170,8 +451,21 @@ impl RegisterStorage for RegisterValueStorage {
       RegisterValueStorage::S64(_) => 4,
       RegisterValueStorage::F32(_) => 2,
       RegisterValueStorage::F64(_) => 4,
-      RegisterValueStorage::String(storage) => storage.value.len() as Quantity,
+      RegisterValueStorage::Bcd16(_) => 1,
+      RegisterValueStorage::Bcd32(_) => 2,
+      RegisterValueStorage::Bcd64(_) => 4,
+      RegisterValueStorage::Sm16(_) => 1,
+      RegisterValueStorage::Sm32(_) => 2,
+      RegisterValueStorage::Sm64(_) => 4,
+      RegisterValueStorage::String(storage) => {
+        storage.value.len().div_ceil(2) as Quantity
+      }
       RegisterValueStorage::Raw(storage) => storage.value.len() as Quantity,
+      RegisterValueStorage::Bits(storage) => storage.kind.length,
+      RegisterValueStorage::Enum(storage) => storage.kind.length,
+      RegisterValueStorage::Datetime(storage) => {
+        datetime_quantity(storage.kind.format)
+      }
     }
   }
 }
@@ -182,36 +476,55 @@ impl Display for RegisterValueStorage {
     f: &mut std::fmt::Formatter<'_>,
   ) -> Result<(), std::fmt::Error> {
     match self {
-      RegisterValueStorage::U16(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
-      }
-      RegisterValueStorage::U32(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
-      }
-      RegisterValueStorage::U64(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
-      }
-      RegisterValueStorage::S16(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
+      RegisterValueStorage::U16(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::U32(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::U64(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::S16(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::S32(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::S64(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::F32(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::F64(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::Bcd16(storage) => {
+        std::fmt::Display::fmt(storage, f)
       }
-      RegisterValueStorage::S32(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
+      RegisterValueStorage::Bcd32(storage) => {
+        std::fmt::Display::fmt(storage, f)
       }
-      RegisterValueStorage::S64(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
-      }
-      RegisterValueStorage::F32(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
-      }
-      RegisterValueStorage::F64(storage) => {
-        std::fmt::Display::fmt(&storage.value, f)
+      RegisterValueStorage::Bcd64(storage) => {
+        std::fmt::Display::fmt(storage, f)
       }
+      RegisterValueStorage::Sm16(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::Sm32(storage) => std::fmt::Display::fmt(storage, f),
+      RegisterValueStorage::Sm64(storage) => std::fmt::Display::fmt(storage, f),
       RegisterValueStorage::String(storage) => {
         std::fmt::Debug::fmt(&storage.value, f)
       }
       RegisterValueStorage::Raw(storage) => {
         std::fmt::Debug::fmt(&storage.value.iter().map(|&num| Hex(num)), f)
       }
+      RegisterValueStorage::Bits(storage) => {
+        std::fmt::Display::fmt(&storage.value, f)
+      }
+      RegisterValueStorage::Enum(storage) => match storage.label() {
+        Some(label) => f.write_str(label),
+        None => std::fmt::Display::fmt(&storage.value, f),
+      },
+      RegisterValueStorage::Datetime(storage) => match &storage.value {
+        Some(value) => f.write_str(value.to_rfc3339().as_str()),
+        None => f.write_str("null"),
+      },
+    }
+  }
+}
+
+impl Display for NumericRegisterValue {
+  fn fmt(
+    &self,
+    f: &mut std::fmt::Formatter<'_>,
+  ) -> Result<(), std::fmt::Error> {
+    match &self.value {
+      Some(value) => std::fmt::Display::fmt(value, f),
+      None => f.write_str("null"),
     }
   }
 }
@@ -242,6 +555,7 @@ pub(crate) fn serialize_registers<
   serde_json::Value::Object(
     registers
       .into_iter()
+      .filter(|register| !register.internal && !register.storage.is_dropped())
       .map(
         |MeasurementRegister::<RegisterValueStorage> {
            name, storage, ..
@@ -251,6 +565,57 @@ pub(crate) fn serialize_registers<
   )
 }
 
+pub(crate) fn apply_scale_factors<
+  TIntoIterator: IntoIterator<Item = MeasurementRegister<RegisterValueStorage>>,
+>(
+  registers: TIntoIterator,
+) -> Vec<MeasurementRegister<RegisterValueStorage>> {
+  let mut registers = registers.into_iter().collect::<Vec<_>>();
+  let exponents = registers
+    .iter()
+    .filter_map(|register| {
+      register.storage.numeric().map(|storage| {
+        (
+          register.name.clone(),
+          storage.value.and_then(|value| i32::try_from(value).ok()),
+        )
+      })
+    })
+    .collect::<Vec<_>>();
+
+  for register in registers.iter_mut() {
+    let Some(scale_factor) = &register.scale_factor else {
+      continue;
+    };
+    let exponent = exponents
+      .iter()
+      .find(|(name, _)| name == scale_factor)
+      .and_then(|(_, exponent)| *exponent);
+    if let Some(storage) = register.storage.numeric_mut() {
+      let offset = storage.offset.unwrap_or_default();
+      // NOTE: the scale factor applies to the raw value so the offset
+      // gets backed out before scaling and added again after
+      storage.value =
+        storage.value.zip(exponent).and_then(|(value, exponent)| {
+          scale_by_power_of_ten(value.checked_sub(offset)?, exponent)?
+            .checked_add(offset)
+        });
+    }
+  }
+
+  registers
+}
+
+fn scale_by_power_of_ten(value: Decimal, exponent: i32) -> Option<Decimal> {
+  let factor = if exponent < 0 {
+    Decimal::try_from_i128_with_scale(1, exponent.unsigned_abs()).ok()?
+  } else {
+    Decimal::from(10u64.checked_pow(exponent.unsigned_abs())?)
+  };
+
+  value.checked_mul(factor)
+}
+
 macro_rules! impl_display {
   ($type: ident) => {
     impl Display for $type<RegisterValueStorage> {
@@ -272,6 +637,10 @@ impl_display!(ValueRegister);
 macro_rules! impl_span {
   ($type: ident) => {
     impl<T: RegisterStorage> Span for $type<T> {
+      fn table(&self) -> RegisterTable {
+        self.table
+      }
+
       fn address(&self) -> Address {
         self.address
       }
@@ -282,6 +651,10 @@ macro_rules! impl_span {
     }
 
     impl<T: RegisterStorage> Span for &$type<T> {
+      fn table(&self) -> RegisterTable {
+        self.table
+      }
+
       fn address(&self) -> Address {
         self.address
       }
@@ -298,71 +671,160 @@ impl_span!(DetectRegister);
 impl_span!(IdRegister);
 impl_span!(ValueRegister);
 
+fn parse_numeric_value(
+  value: Decimal,
+  missing: bool,
+  kind: &NumericRegisterKind,
+) -> anyhow::Result<Option<Decimal>> {
+  if missing {
+    return Ok(match kind.missing {
+      MissingValuePolicy::Zero => Some(Decimal::ZERO),
+      MissingValuePolicy::Null | MissingValuePolicy::Drop => None,
+    });
+  }
+
+  let value = match kind.multiplier {
+    Some(multiplier) => value
+      .checked_mul(multiplier)
+      .ok_or_else(|| anyhow::anyhow!("Failed multiplying register"))?,
+    None => value,
+  };
+
+  Ok(Some(match kind.offset {
+    Some(offset) => value
+      .checked_add(offset)
+      .ok_or_else(|| anyhow::anyhow!("Failed offsetting register"))?,
+    None => value,
+  }))
+}
+
 macro_rules! parse_integer_register {
-  ($variant: ident, $type: ty, $data: ident, $multiplier: ident, $timestamp: expr) => {{
-    let bytes = decode_numeric_bytes($data);
+  ($variant: ident, $type: ty, $data: ident, $kind: ident, $timestamp: expr) => {{
+    let bytes =
+      decode_numeric_bytes(reorder_numeric_words($data, $kind.endianness));
     let slice = bytes.as_slice().try_into()?;
-    let mut typed = <$type>::from_ne_bytes(slice);
-    if (typed == <$type>::MAX) {
-      typed = 0;
-    }
-
+    let typed = <$type>::from_ne_bytes(slice);
     let value = Decimal::from(typed);
-    RegisterValueStorage::$variant(RegisterValue::<Decimal> {
-      value: match $multiplier {
-        Some($multiplier) => value
-          .checked_mul($multiplier)
-          .ok_or_else(|| anyhow::anyhow!("Failed multiplying register"))?,
-        None => value,
-      },
+    let missing = match &$kind.sentinels {
+      Some(sentinels) => sentinels.contains(&value),
+      None => typed == <$type>::MAX,
+    };
+    RegisterValueStorage::$variant(NumericRegisterValue {
+      value: parse_numeric_value(value, missing, $kind)?,
       timestamp: $timestamp,
+      endianness: $kind.endianness,
+      missing: $kind.missing,
+      multiplier: $kind.multiplier,
+      offset: $kind.offset,
     })
   }};
 }
 
 macro_rules! parse_floating_register {
-  ($variant: ident, $type: ty, $data: ident, $multiplier: ident, $timestamp: expr) => {{
-    let bytes = decode_numeric_bytes($data);
+  ($variant: ident, $type: ty, $data: ident, $kind: ident, $timestamp: expr) => {{
+    let bytes =
+      decode_numeric_bytes(reorder_numeric_words($data, $kind.endianness));
     let slice = bytes.as_slice().try_into()?;
     let value = Decimal::try_from(<$type>::from_ne_bytes(slice))?;
-    RegisterValueStorage::$variant(RegisterValue::<Decimal> {
-      value: match $multiplier {
-        Some($multiplier) => value
-          .checked_mul($multiplier)
-          .ok_or_else(|| anyhow::anyhow!("Failed multiplying register"))?,
-        None => value,
-      },
+    let missing = match &$kind.sentinels {
+      Some(sentinels) => sentinels.contains(&value),
+      None => false,
+    };
+    RegisterValueStorage::$variant(NumericRegisterValue {
+      value: parse_numeric_value(value, missing, $kind)?,
       timestamp: $timestamp,
+      endianness: $kind.endianness,
+      missing: $kind.missing,
+      multiplier: $kind.multiplier,
+      offset: $kind.offset,
+    })
+  }};
+}
+
+macro_rules! parse_bcd_register {
+  ($variant: ident, $data: ident, $kind: ident, $timestamp: expr) => {{
+    let decoded =
+      decode_bcd_words(reorder_numeric_words($data, $kind.endianness));
+    let value = decoded.map(Decimal::from).unwrap_or_default();
+    let missing = match &$kind.sentinels {
+      Some(sentinels) => decoded.is_none() || sentinels.contains(&value),
+      None => decoded.is_none(),
+    };
+    RegisterValueStorage::$variant(NumericRegisterValue {
+      value: parse_numeric_value(value, missing, $kind)?,
+      timestamp: $timestamp,
+      endianness: $kind.endianness,
+      missing: $kind.missing,
+      multiplier: $kind.multiplier,
+      offset: $kind.offset,
+    })
+  }};
+}
+
+macro_rules! parse_sign_magnitude_register {
+  ($variant: ident, $data: ident, $kind: ident, $timestamp: expr) => {{
+    let value = Decimal::from(decode_sign_magnitude_words(
+      reorder_numeric_words($data, $kind.endianness),
+    ));
+    let missing = match &$kind.sentinels {
+      Some(sentinels) => sentinels.contains(&value),
+      None => false,
+    };
+    RegisterValueStorage::$variant(NumericRegisterValue {
+      value: parse_numeric_value(value, missing, $kind)?,
+      timestamp: $timestamp,
+      endianness: $kind.endianness,
+      missing: $kind.missing,
+      multiplier: $kind.multiplier,
+      offset: $kind.offset,
     })
   }};
 }
 
 macro_rules! parse_register {
   ($self: ident, $data: ident, $result: expr, $timestamp: expr) => {{
-    let value = match $self.storage {
-      RegisterKindStorage::U16(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(U16, u16, $data, multiplier, $timestamp)
+    let value = match &$self.storage {
+      RegisterKindStorage::U16(kind) => {
+        parse_integer_register!(U16, u16, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::U32(kind) => {
+        parse_integer_register!(U32, u32, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::U64(kind) => {
+        parse_integer_register!(U64, u64, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::S16(kind) => {
+        parse_integer_register!(S16, i16, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::S32(kind) => {
+        parse_integer_register!(S32, i32, $data, kind, $timestamp)
       }
-      RegisterKindStorage::U32(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(U32, u32, $data, multiplier, $timestamp)
+      RegisterKindStorage::S64(kind) => {
+        parse_integer_register!(S64, i64, $data, kind, $timestamp)
       }
-      RegisterKindStorage::U64(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(U64, u64, $data, multiplier, $timestamp)
+      RegisterKindStorage::F32(kind) => {
+        parse_floating_register!(F32, f32, $data, kind, $timestamp)
       }
-      RegisterKindStorage::S16(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(S16, i16, $data, multiplier, $timestamp)
+      RegisterKindStorage::F64(kind) => {
+        parse_floating_register!(F64, f64, $data, kind, $timestamp)
       }
-      RegisterKindStorage::S32(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(S32, i32, $data, multiplier, $timestamp)
+      RegisterKindStorage::Bcd16(kind) => {
+        parse_bcd_register!(Bcd16, $data, kind, $timestamp)
       }
-      RegisterKindStorage::S64(NumericRegisterKind { multiplier }) => {
-        parse_integer_register!(S64, i64, $data, multiplier, $timestamp)
+      RegisterKindStorage::Bcd32(kind) => {
+        parse_bcd_register!(Bcd32, $data, kind, $timestamp)
       }
-      RegisterKindStorage::F32(NumericRegisterKind { multiplier }) => {
-        parse_floating_register!(F32, f32, $data, multiplier, $timestamp)
+      RegisterKindStorage::Bcd64(kind) => {
+        parse_bcd_register!(Bcd64, $data, kind, $timestamp)
       }
-      RegisterKindStorage::F64(NumericRegisterKind { multiplier }) => {
-        parse_floating_register!(F64, f64, $data, multiplier, $timestamp)
+      RegisterKindStorage::Sm16(kind) => {
+        parse_sign_magnitude_register!(Sm16, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::Sm32(kind) => {
+        parse_sign_magnitude_register!(Sm32, $data, kind, $timestamp)
+      }
+      RegisterKindStorage::Sm64(kind) => {
+        parse_sign_magnitude_register!(Sm64, $data, kind, $timestamp)
       }
       RegisterKindStorage::String(_) => {
         let bytes = decode_string_bytes($data);
@@ -377,6 +839,27 @@ macro_rules! parse_register {
           timestamp: $timestamp,
         })
       }
+      RegisterKindStorage::Bits(kind) => {
+        RegisterValueStorage::Bits(BitsRegisterValue {
+          value: decode_flag_words($data),
+          timestamp: $timestamp,
+          kind: kind.clone(),
+        })
+      }
+      RegisterKindStorage::Enum(kind) => {
+        RegisterValueStorage::Enum(EnumRegisterValue {
+          value: decode_flag_words($data),
+          timestamp: $timestamp,
+          kind: kind.clone(),
+        })
+      }
+      RegisterKindStorage::Datetime(kind) => {
+        RegisterValueStorage::Datetime(DatetimeRegisterValue {
+          value: decode_datetime($data, kind.format, kind.timezone),
+          timestamp: $timestamp,
+          kind: *kind,
+        })
+      }
     };
 
     #[allow(clippy::redundant_closure_call)]
@@ -389,17 +872,6 @@ macro_rules! impl_parse_register {
     impl SpanParser<$type<RegisterValueStorage>>
       for $type<RegisterKindStorage>
     {
-      fn parse<TIterator, TIntoIterator>(
-        &self,
-        data: TIntoIterator,
-      ) -> anyhow::Result<$type<RegisterValueStorage>>
-      where
-        TIterator: DoubleEndedIterator<Item = u16>,
-        TIntoIterator: IntoIterator<Item = u16, IntoIter = TIterator>,
-      {
-        parse_register!(self, data, $result, chrono::Utc::now())
-      }
-
       fn parse_with_timestamp<TIterator, TIntoIterator>(
         &self,
         data: TIntoIterator,
@@ -416,17 +888,6 @@ macro_rules! impl_parse_register {
     impl SpanParser<$type<RegisterValueStorage>>
       for &$type<RegisterKindStorage>
     {
-      fn parse<TIterator, TIntoIterator>(
-        &self,
-        data: TIntoIterator,
-      ) -> anyhow::Result<$type<RegisterValueStorage>>
-      where
-        TIterator: DoubleEndedIterator<Item = u16>,
-        TIntoIterator: IntoIterator<Item = u16, IntoIter = TIterator>,
-      {
-        parse_register!(self, data, $result, chrono::Utc::now())
-      }
-
       fn parse_with_timestamp<TIterator, TIntoIterator>(
         &self,
         data: TIntoIterator,
@@ -446,9 +907,12 @@ impl_parse_register!(
   MeasurementRegister,
   |register: &MeasurementRegister::<RegisterKindStorage>, storage| {
     MeasurementRegister::<RegisterValueStorage> {
+      table: register.table,
       address: register.address,
       storage,
       name: register.name.clone(),
+      scale_factor: register.scale_factor.clone(),
+      internal: register.internal,
     }
   }
 );
@@ -457,6 +921,7 @@ impl_parse_register!(
   DetectRegister,
   |register: &DetectRegister::<RegisterKindStorage>, storage| {
     DetectRegister::<RegisterValueStorage> {
+      table: register.table,
       address: register.address,
       storage,
       r#match: register.r#match.clone(),
@@ -469,6 +934,7 @@ impl_parse_register!(IdRegister, |register: &IdRegister::<
 >,
                                   storage| {
   IdRegister::<RegisterValueStorage> {
+    table: register.table,
     address: register.address,
     storage,
   }
@@ -478,46 +944,101 @@ impl_parse_register!(
   ValueRegister,
   |register: &ValueRegister::<RegisterKindStorage>, storage| {
     ValueRegister::<RegisterValueStorage> {
+      table: register.table,
       address: register.address,
       storage,
     }
   }
 );
 
+// NOTE: inverse of parse_numeric_value so devices get the raw value back
+fn unscale_numeric_value(storage: &NumericRegisterValue) -> Option<Decimal> {
+  let value = match storage.offset {
+    Some(offset) => storage.value?.checked_sub(offset)?,
+    None => storage.value?,
+  };
+
+  match storage.multiplier {
+    Some(multiplier) => value.checked_div(multiplier),
+    None => Some(value),
+  }
+}
+
 macro_rules! serialize_numeric_register {
-  ($type: ty, $value: ident, $default: expr) => {{
-    let value = TryInto::<u16>::try_into(*$value).unwrap_or(0u16);
-    let bytes = value.to_ne_bytes();
-    encode_numeric_bytes(bytes).into_iter()
+  ($type: ty, $storage: ident, $default: expr) => {{
+    let value = unscale_numeric_value($storage).unwrap_or_default();
+    let value = TryInto::<$type>::try_into(value).unwrap_or($default);
+    let bytes = value.to_be_bytes();
+    reorder_numeric_words(encode_numeric_bytes(bytes), $storage.endianness)
+      .into_iter()
+  }};
+}
+
+macro_rules! serialize_bcd_register {
+  ($length: expr, $storage: ident) => {{
+    let value = unscale_numeric_value($storage).unwrap_or_default().trunc();
+    let value = TryInto::<u64>::try_into(value).unwrap_or(0u64);
+    reorder_numeric_words(encode_bcd_words(value, $length), $storage.endianness)
+      .into_iter()
+  }};
+}
+
+macro_rules! serialize_sign_magnitude_register {
+  ($length: expr, $storage: ident) => {{
+    let value = unscale_numeric_value($storage).unwrap_or_default().trunc();
+    let value = TryInto::<i64>::try_into(value).unwrap_or(0i64);
+    reorder_numeric_words(
+      encode_sign_magnitude_words(value, $length),
+      $storage.endianness,
+    )
+    .into_iter()
   }};
 }
 
 macro_rules! serialize_register {
   ($self: ident) => {{
     match &$self.storage {
-      RegisterValueStorage::U16(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(u16, value, 0u16)
+      RegisterValueStorage::U16(storage) => {
+        serialize_numeric_register!(u16, storage, 0u16)
+      }
+      RegisterValueStorage::U32(storage) => {
+        serialize_numeric_register!(u32, storage, 0u32)
+      }
+      RegisterValueStorage::U64(storage) => {
+        serialize_numeric_register!(u64, storage, 0u64)
       }
-      RegisterValueStorage::U32(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(u32, value, 0u32)
+      RegisterValueStorage::S16(storage) => {
+        serialize_numeric_register!(i16, storage, 0i16)
+      }
+      RegisterValueStorage::S32(storage) => {
+        serialize_numeric_register!(i32, storage, 0i32)
+      }
+      RegisterValueStorage::S64(storage) => {
+        serialize_numeric_register!(i64, storage, 0i64)
+      }
+      RegisterValueStorage::F32(storage) => {
+        serialize_numeric_register!(f32, storage, 0f32)
+      }
+      RegisterValueStorage::F64(storage) => {
+        serialize_numeric_register!(f64, storage, 0f64)
       }
-      RegisterValueStorage::U64(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(u64, value, 0u64)
+      RegisterValueStorage::Bcd16(storage) => {
+        serialize_bcd_register!(1, storage)
       }
-      RegisterValueStorage::S16(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(i16, value, 0i16)
+      RegisterValueStorage::Bcd32(storage) => {
+        serialize_bcd_register!(2, storage)
       }
-      RegisterValueStorage::S32(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(i32, value, 0i32)
+      RegisterValueStorage::Bcd64(storage) => {
+        serialize_bcd_register!(4, storage)
       }
-      RegisterValueStorage::S64(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(i64, value, 0i64)
+      RegisterValueStorage::Sm16(storage) => {
+        serialize_sign_magnitude_register!(1, storage)
       }
-      RegisterValueStorage::F32(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(f32, value, 0f32)
+      RegisterValueStorage::Sm32(storage) => {
+        serialize_sign_magnitude_register!(2, storage)
       }
-      RegisterValueStorage::F64(RegisterValue::<Decimal> { value, .. }) => {
-        serialize_numeric_register!(f64, value, 0f64)
+      RegisterValueStorage::Sm64(storage) => {
+        serialize_sign_magnitude_register!(4, storage)
       }
       RegisterValueStorage::String(RegisterValue::<String> {
         value, ..
@@ -525,6 +1046,21 @@ macro_rules! serialize_register {
       RegisterValueStorage::Raw(RegisterValue::<Vec<u16>> {
         value, ..
       }) => value.clone().into_iter(),
+      RegisterValueStorage::Bits(BitsRegisterValue { value, kind, .. }) => {
+        encode_flag_words(*value, kind.length).into_iter()
+      }
+      RegisterValueStorage::Enum(EnumRegisterValue { value, kind, .. }) => {
+        encode_flag_words(*value, kind.length).into_iter()
+      }
+      RegisterValueStorage::Datetime(DatetimeRegisterValue {
+        value,
+        kind,
+        ..
+      }) => match value {
+        Some(value) => encode_datetime(*value, kind.format, kind.timezone),
+        None => vec![0u16; datetime_quantity(kind.format) as usize],
+      }
+      .into_iter(),
     }
   }};
 }
@@ -549,3 +1085,245 @@ impl_record!(MeasurementRegister);
 impl_record!(DetectRegister);
 impl_record!(IdRegister);
 impl_record!(ValueRegister);
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)] // NOTE: tests fail loudly on purpose
+mod tests {
+  use super::*;
+
+  fn measurement_register(
+    storage: RegisterKindStorage,
+  ) -> MeasurementRegister<RegisterKindStorage> {
+    MeasurementRegister::<RegisterKindStorage> {
+      table: RegisterTable::Holding,
+      address: 0,
+      storage,
+      name: "value".to_string(),
+      scale_factor: None,
+      internal: false,
+    }
+  }
+
+  fn numeric_kind(endianness: Endianness) -> NumericRegisterKind {
+    NumericRegisterKind {
+      multiplier: Some(Decimal::new(1, 1)),
+      offset: Some(Decimal::new(-5, 0)),
+      endianness,
+      sentinels: None,
+      missing: MissingValuePolicy::Null,
+    }
+  }
+
+  fn plain_kind(endianness: Endianness) -> NumericRegisterKind {
+    NumericRegisterKind {
+      multiplier: None,
+      offset: None,
+      endianness,
+      sentinels: Some(Vec::new()),
+      missing: MissingValuePolicy::Null,
+    }
+  }
+
+  fn numeric_value(
+    value: Decimal,
+    endianness: Endianness,
+  ) -> NumericRegisterValue {
+    NumericRegisterValue {
+      value: Some(value),
+      timestamp: chrono::Utc::now(),
+      endianness,
+      missing: MissingValuePolicy::Null,
+      multiplier: None,
+      offset: None,
+    }
+  }
+
+  fn value_register(
+    storage: RegisterValueStorage,
+  ) -> MeasurementRegister<RegisterValueStorage> {
+    MeasurementRegister::<RegisterValueStorage> {
+      table: RegisterTable::Holding,
+      address: 0,
+      storage,
+      name: "value".to_string(),
+      scale_factor: None,
+      internal: false,
+    }
+  }
+
+  type KindVariant = fn(NumericRegisterKind) -> RegisterKindStorage;
+  type ValueVariant = fn(NumericRegisterValue) -> RegisterValueStorage;
+
+  #[test]
+  fn numeric_values_round_trip() {
+    let cases: [(KindVariant, ValueVariant, Decimal); 14] = [
+      (
+        RegisterKindStorage::U16,
+        RegisterValueStorage::U16,
+        Decimal::from(2060),
+      ),
+      (
+        RegisterKindStorage::U32,
+        RegisterValueStorage::U32,
+        Decimal::from(0x1234_5678),
+      ),
+      (
+        RegisterKindStorage::U64,
+        RegisterValueStorage::U64,
+        Decimal::from(0x0123_4567_89AB_CDEFu64),
+      ),
+      (
+        RegisterKindStorage::S16,
+        RegisterValueStorage::S16,
+        Decimal::from(-1234),
+      ),
+      (
+        RegisterKindStorage::S32,
+        RegisterValueStorage::S32,
+        Decimal::from(-123_456_789),
+      ),
+      (
+        RegisterKindStorage::S64,
+        RegisterValueStorage::S64,
+        Decimal::from(-1_234_567_890_123i64),
+      ),
+      (
+        RegisterKindStorage::F32,
+        RegisterValueStorage::F32,
+        Decimal::new(2305, 1),
+      ),
+      (
+        RegisterKindStorage::F64,
+        RegisterValueStorage::F64,
+        Decimal::new(-123_425, 2),
+      ),
+      (
+        RegisterKindStorage::Bcd16,
+        RegisterValueStorage::Bcd16,
+        Decimal::from(1234),
+      ),
+      (
+        RegisterKindStorage::Bcd32,
+        RegisterValueStorage::Bcd32,
+        Decimal::from(12_345_678),
+      ),
+      (
+        RegisterKindStorage::Bcd64,
+        RegisterValueStorage::Bcd64,
+        Decimal::from(1_234_567_890_123_456u64),
+      ),
+      (
+        RegisterKindStorage::Sm16,
+        RegisterValueStorage::Sm16,
+        Decimal::from(-1234),
+      ),
+      (
+        RegisterKindStorage::Sm32,
+        RegisterValueStorage::Sm32,
+        Decimal::from(-123_456_789),
+      ),
+      (
+        RegisterKindStorage::Sm64,
+        RegisterValueStorage::Sm64,
+        Decimal::from(-1_234_567_890_123i64),
+      ),
+    ];
+
+    for endianness in [
+      Endianness::Abcd,
+      Endianness::Cdab,
+      Endianness::Badc,
+      Endianness::Dcba,
+    ] {
+      for (kind, value, expected) in cases {
+        let words = value_register(value(numeric_value(expected, endianness)))
+          .values()
+          .collect::<Vec<_>>();
+        let register = measurement_register(kind(plain_kind(endianness)));
+        let parsed = register
+          .parse_with_timestamp(words, chrono::Utc::now())
+          .unwrap();
+        let parsed = parsed.storage.numeric().and_then(|value| value.value);
+        assert_eq!(parsed, Some(expected), "{endianness:?} {expected}");
+      }
+    }
+  }
+
+  #[test]
+  fn numeric_values_serialise_most_significant_word_first() {
+    for (storage, words) in [
+      (
+        RegisterValueStorage::U16(numeric_value(
+          Decimal::from(2060),
+          Endianness::Abcd,
+        )),
+        vec![0x080C],
+      ),
+      (
+        RegisterValueStorage::U32(numeric_value(
+          Decimal::from(0x1234_5678),
+          Endianness::Abcd,
+        )),
+        vec![0x1234, 0x5678],
+      ),
+      (
+        RegisterValueStorage::U32(numeric_value(
+          Decimal::from(0x1234_5678),
+          Endianness::Cdab,
+        )),
+        vec![0x5678, 0x1234],
+      ),
+      (
+        RegisterValueStorage::F32(numeric_value(
+          Decimal::from(230),
+          Endianness::Abcd,
+        )),
+        vec![0x4366, 0x0000],
+      ),
+    ] {
+      let register = value_register(storage);
+      assert_eq!(register.values().collect::<Vec<_>>(), words);
+    }
+  }
+
+  #[test]
+  fn scaled_bcd_and_sign_magnitude_round_trip() {
+    for endianness in [
+      Endianness::Abcd,
+      Endianness::Cdab,
+      Endianness::Badc,
+      Endianness::Dcba,
+    ] {
+      let kind = numeric_kind(endianness);
+      for (storage, words) in [
+        (
+          RegisterKindStorage::Bcd32(kind.clone()),
+          vec![0x0012, 0x3456],
+        ),
+        (
+          RegisterKindStorage::Sm32(kind.clone()),
+          vec![0x8001, 0x0002],
+        ),
+      ] {
+        let register = measurement_register(storage);
+        let parsed = register
+          .parse_with_timestamp(words.clone(), chrono::Utc::now())
+          .unwrap();
+        assert_eq!(parsed.values().collect::<Vec<_>>(), words);
+      }
+    }
+  }
+
+  #[test]
+  fn serialise_reverses_multiplier_and_offset() {
+    let register = measurement_register(RegisterKindStorage::Bcd16(
+      numeric_kind(Endianness::Abcd),
+    ));
+    let parsed = register
+      .parse_with_timestamp(vec![0x1234], chrono::Utc::now())
+      .unwrap();
+    let value = parsed.storage.numeric().and_then(|storage| storage.value);
+    assert_eq!(value, Some(Decimal::new(11_840, 2)));
+    assert_eq!(parsed.values().collect::<Vec<_>>(), vec![0x1234]);
+  }
+}
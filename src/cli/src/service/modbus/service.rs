@@ -1,4 +1,4 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
 
 use futures::StreamExt;
 use futures_core::Stream;
@@ -7,8 +7,12 @@ use tokio::sync::Mutex;
 use crate::*;
 
 use super::batch::*;
-use super::connection::Destination;
+use super::concentrator::{Concentrator, ServeError};
+use super::connection::{Destination, Transport};
+use super::identification::Identification;
+use super::metrics::GatewayMetrics;
 use super::record::Record;
+use super::register::{MeasurementRegister, RegisterValueStorage};
 use super::span::*;
 use super::worker::*;
 
@@ -20,12 +24,19 @@ pub(crate) type WriteResponse = Vec<chrono::DateTime<chrono::Utc>>;
 #[derive(Clone, Debug)]
 pub(crate) struct Service {
   devices: Arc<Mutex<HashMap<String, Device>>>,
-  servers: Arc<Mutex<HashMap<SocketAddr, Server>>>,
+  servers: Arc<Mutex<HashMap<Transport, Server>>>,
   read_timeout: chrono::Duration,
   batch_threshold: u16,
+  batch_max_quantity: u16,
+  adaptive_batching: bool,
   termination_timeout: chrono::Duration,
   congestion_backoff: chrono::Duration,
   partial_retries: u32,
+  queue: Queue,
+  gateways: Vec<config::Gateway>,
+  concentrator: Option<Concentrator>,
+  // NOTE: metrics persisted before a restart keyed by gateway
+  baseline: Arc<Mutex<HashMap<String, GatewayMetrics>>>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -36,6 +47,15 @@ pub(crate) enum ServerReadError {
   #[error("Server failure")]
   ServerFailed(anyhow::Error),
 
+  #[error("Server responded with exception {0:?}")]
+  Exception(super::connection::Exception),
+
+  #[error("Server is overloaded")]
+  Overloaded,
+
+  #[error("Request deadline exceeded")]
+  DeadlineExceeded,
+
   #[error("Parsing failure")]
   ParsingFailed(anyhow::Error),
 }
@@ -45,12 +65,24 @@ pub(crate) enum ServerWriteError {
   #[error("Connection failed")]
   FailedToConnect(#[from] super::connection::ConnectError),
 
+  #[error("Server responded with exception {0:?}")]
+  Exception(super::connection::Exception),
+
+  #[error("Server is overloaded")]
+  Overloaded,
+
+  #[error("Request deadline exceeded")]
+  DeadlineExceeded,
+
   #[error("Server failure")]
   ServerFailed(anyhow::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum ServerStreamError {
+  #[error("Server is overloaded")]
+  Overloaded,
+
   #[error("Server failure")]
   ServerFailed(anyhow::Error),
 }
@@ -89,24 +121,39 @@ impl service::Service for Service {
       servers: Arc::new(Mutex::new(HashMap::new())),
       read_timeout: config.modbus.read_timeout,
       batch_threshold: config.modbus.batch_threshold,
+      batch_max_quantity: config.modbus.batch_max_quantity,
+      adaptive_batching: config.modbus.adaptive_batching,
       termination_timeout: config.modbus.termination_timeout,
       congestion_backoff: config.modbus.congestion_backoff,
       partial_retries: config.modbus.partial_retries,
+      queue: Queue {
+        capacity: config.modbus.queue_capacity,
+        deadline: config.modbus.request_deadline,
+      },
+      gateways: config.modbus.gateways,
+      concentrator: config.modbus.concentrator.map(Concentrator::new),
+      baseline: Arc::new(Mutex::new(HashMap::new())),
     }
   }
 }
 
 impl Service {
   #[tracing::instrument(skip(self))]
-  pub(crate) async fn bind(&self, id: String, destination: Destination) {
-    let server = self.get_server(destination).await;
+  pub(crate) async fn bind(
+    &self,
+    id: String,
+    destination: Destination,
+    batching: Batching,
+  ) {
+    let server = self.get_server(&destination).await;
     {
       let mut devices = self.devices.clone().lock_owned().await;
       devices.insert(
         id,
         Device {
-          worker: server.worker,
+          worker: server.worker_for(&destination),
           destination,
+          batching,
         },
       );
 
@@ -114,6 +161,127 @@ impl Service {
     }
   }
 
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn plan_from_id(&self, id: &str) -> Option<Plan> {
+    let device = self.get_device(id).await?;
+    device.worker.plan(&device.destination).await
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn seed_plan_from_id(&self, id: &str, plan: Plan) {
+    if let Some(device) = self.get_device(id).await {
+      device.worker.seed(device.destination, plan).await;
+    }
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn splits_from_id(&self, id: &str) -> Vec<SimpleSpan> {
+    match self.get_device(id).await {
+      Some(device) => device
+        .worker
+        .splits(&device.destination)
+        .await
+        .into_iter()
+        .collect(),
+      None => Vec::new(),
+    }
+  }
+
+  #[tracing::instrument(skip(self, splits))]
+  pub(crate) async fn seed_splits_from_id<
+    TIntoIterator: IntoIterator<Item = SimpleSpan>,
+  >(
+    &self,
+    id: &str,
+    splits: TIntoIterator,
+  ) {
+    if let Some(device) = self.get_device(id).await {
+      device.worker.seed_splits(device.destination, splits).await;
+    }
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn serve(&self) -> Result<(), ServeError> {
+    match &self.concentrator {
+      Some(concentrator) => concentrator.serve().await,
+      None => Ok(()),
+    }
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn stop_serving(&self) {
+    if let Some(concentrator) = &self.concentrator {
+      concentrator.stop().await;
+    }
+  }
+
+  #[tracing::instrument(skip(self, registers))]
+  pub(crate) async fn publish(
+    &self,
+    id: &str,
+    registers: &[MeasurementRegister<RegisterValueStorage>],
+  ) {
+    if let Some(concentrator) = &self.concentrator {
+      concentrator.publish(id, registers).await;
+    }
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn metrics(&self) -> Vec<GatewayMetrics> {
+    let servers = self
+      .servers
+      .clone()
+      .lock_owned()
+      .await
+      .iter()
+      .map(|(transport, server)| (transport.clone(), server.clone()))
+      .collect::<Vec<_>>();
+    let ids = self
+      .devices
+      .clone()
+      .lock_owned()
+      .await
+      .iter()
+      .map(|(id, device)| (device.destination.clone(), id.clone()))
+      .collect::<HashMap<_, _>>();
+
+    let baseline = self.baseline.clone().lock_owned().await;
+
+    let mut metrics = Vec::with_capacity(servers.len());
+    for (transport, server) in servers {
+      let totals = server.totals().await;
+      let mut gateway = GatewayMetrics::new(
+        &transport,
+        server.drains(),
+        totals.into_iter().map(|(destination, counters)| {
+          let id = ids.get(&destination).cloned();
+          (destination, id, counters)
+        }),
+      );
+      if let Some(persisted) = baseline.get(&gateway.gateway) {
+        gateway.merge(persisted);
+      }
+      metrics.push(gateway);
+    }
+
+    metrics
+  }
+
+  #[tracing::instrument(skip_all)]
+  pub(crate) async fn seed_metrics<
+    TIntoIterator: IntoIterator<Item = GatewayMetrics>,
+  >(
+    &self,
+    metrics: TIntoIterator,
+  ) {
+    let mut baseline = self.baseline.clone().lock_owned().await;
+    baseline.extend(
+      metrics
+        .into_iter()
+        .map(|metrics| (metrics.gateway.clone(), metrics)),
+    );
+  }
+
   #[tracing::instrument(skip(self))]
   pub(crate) async fn stop_from_id(&self, id: &str) {
     let mut server_to_remove = None;
@@ -123,11 +291,11 @@ impl Service {
       let device = devices.remove(id);
       if let Some(removed) = device {
         let should_remove_server = !devices.values().any(|device| {
-          device.destination.address == removed.destination.address
+          device.destination.transport == removed.destination.transport
         });
 
         if should_remove_server {
-          server_to_remove = Some(removed.destination.address);
+          server_to_remove = Some(removed.destination.transport);
         }
       }
 
@@ -139,7 +307,7 @@ impl Service {
     }
 
     if let Some(server) = server_to_remove {
-      self.stop_from_address(server).await;
+      self.stop_from_transport(server).await;
     }
   }
 
@@ -161,11 +329,11 @@ impl Service {
         .filter_map(|id| {
           devices.remove(id).and_then(|removed| {
             let should_remove_server = !devices.values().any(|device| {
-              device.destination.address == removed.destination.address
+              device.destination.transport == removed.destination.transport
             });
 
             if should_remove_server {
-              Some(removed.destination.address)
+              Some(removed.destination.transport)
             } else {
               None
             }
@@ -183,21 +351,21 @@ impl Service {
     let mut removed_servers = Vec::new();
     {
       let mut servers = self.servers.clone().lock_owned().await;
-      for address in servers_to_remove {
-        let server = servers.remove(&address);
+      for transport in servers_to_remove {
+        let server = servers.remove(&transport);
         if let Some(server) = server {
           removed_servers.push(server);
         }
       }
 
       tracing::trace!(
-        "Removed servers - remaining addresses {:?}",
+        "Removed servers - remaining transports {:?}",
         servers.keys(),
       );
     }
 
     for server in removed_servers {
-      if let Err(error) = server.worker.terminate().await {
+      if let Err(error) = server.terminate().await {
         // NOTE: error -> trace because this means it already terminated and disconnected
         tracing::trace!("Failed terminating server worker {}", error)
       }
@@ -205,22 +373,22 @@ impl Service {
   }
 
   #[tracing::instrument(skip(self))]
-  pub(crate) async fn stop_from_address(&self, address: SocketAddr) {
+  pub(crate) async fn stop_from_transport(&self, transport: Transport) {
     {
       let mut devices = self.devices.clone().lock_owned().await;
-      devices.retain(|_, device| device.destination.address != address);
+      devices.retain(|_, device| device.destination.transport != transport);
       tracing::trace!("Retained devices {:?}", devices.keys());
     }
 
     let server = {
       let mut servers = self.servers.clone().lock_owned().await;
-      servers.remove(&address)
+      servers.remove(&transport)
     };
 
     if let Some(server) = server {
       tracing::trace!("Removed {:?} server", server);
 
-      if let Err(error) = server.worker.terminate().await {
+      if let Err(error) = server.terminate().await {
         // NOTE: error -> trace because this means it already terminated and disconnected
         tracing::trace!("Failed terminating server worker {}", error)
       }
@@ -236,11 +404,17 @@ impl Service {
   >(
     &self,
     destination: Destination,
+    batching: &Batching,
     spans: TIntoIterator,
   ) -> Result<ReadResponse<TSpan>, ServerReadError> {
-    let server = self.get_server(destination).await;
+    let server = self.get_server(&destination).await;
     let response = self
-      .read_from_worker(server.worker, destination, spans)
+      .read_from_worker(
+        server.worker_for(&destination),
+        destination,
+        batching,
+        spans,
+      )
       .await?;
 
     tracing::trace!("Read {:?} spans", response.len());
@@ -248,6 +422,22 @@ impl Service {
     Ok(response)
   }
 
+  #[tracing::instrument(skip(self))]
+  pub(crate) async fn identify_destination(
+    &self,
+    destination: Destination,
+  ) -> Result<Identification, IdentifyError> {
+    let server = self.get_server(&destination).await;
+    let identification = server
+      .worker_for(&destination)
+      .identify(destination)
+      .await?;
+
+    tracing::trace!("Identified {:?}", identification);
+
+    Ok(identification)
+  }
+
   #[tracing::instrument(skip(self, records))]
   pub(crate) async fn write_to_destination<
     TRecord: Record,
@@ -258,9 +448,9 @@ impl Service {
     destination: Destination,
     records: TIntoIterator,
   ) -> Result<WriteResponse, ServerWriteError> {
-    let server = self.get_server(destination).await;
+    let server = self.get_server(&destination).await;
     let response = self
-      .write_to_worker(server.worker, destination, records)
+      .write_to_worker(server.worker_for(&destination), destination, records)
       .await?;
 
     tracing::trace!("Wrote {:?} records", response.len());
@@ -277,14 +467,22 @@ impl Service {
   >(
     &self,
     destination: Destination,
+    batching: &Batching,
     spans: TIntoIterator,
+    interval: Option<chrono::Duration>,
   ) -> Result<
     impl Stream<Item = Result<Vec<TSpan>, ServerReadError>>,
     ServerStreamError,
   > {
-    let server = self.get_server(destination).await;
+    let server = self.get_server(&destination).await;
     let stream = self
-      .stream_from_worker(server.worker, destination, spans)
+      .stream_from_worker(
+        server.worker_for(&destination),
+        destination,
+        batching,
+        spans,
+        interval,
+      )
       .await?;
 
     tracing::trace!("Streaming spans");
@@ -308,7 +506,12 @@ impl Service {
       None => return Err(DeviceReadError::DeviceNotFound(id.to_string())),
     };
     let response = self
-      .read_from_worker(device.worker, device.destination, spans)
+      .read_from_worker(
+        device.worker,
+        device.destination,
+        &device.batching,
+        spans,
+      )
       .await?;
 
     tracing::trace!("Read {:?} spans", response.len());
@@ -349,6 +552,7 @@ impl Service {
     &self,
     id: &str,
     spans: TIntoIterator,
+    interval: Option<chrono::Duration>,
   ) -> Result<
     impl Stream<Item = Result<Vec<TSpan>, ServerReadError>>,
     DeviceStreamError,
@@ -358,7 +562,13 @@ impl Service {
       None => return Err(DeviceStreamError::DeviceNotFound(id.to_string())),
     };
     let stream = self
-      .stream_from_worker(device.worker, device.destination, spans)
+      .stream_from_worker(
+        device.worker,
+        device.destination,
+        &device.batching,
+        spans,
+        interval,
+      )
       .await?;
 
     tracing::trace!("Streaming spans");
@@ -375,11 +585,14 @@ impl Service {
     &self,
     worker: Worker,
     destination: Destination,
+    batching: &Batching,
     spans: TIntoIterator,
   ) -> Result<ReadResponse<TSpan>, ServerReadError> {
     let iter = spans.into_iter();
     let len = iter.len();
-    let batches = batch_spans(iter, self.batch_threshold);
+    let (threshold, max_quantity) =
+      self.batch_limits(worker.plan(&destination).await, batching);
+    let batches = batch_spans(iter, threshold, max_quantity, &batching.holes);
     let result = worker.read(destination, batches.iter()).await;
     let response = Self::parse_worker_read_response(result, batches, len)?;
     Ok(response)
@@ -410,19 +623,31 @@ impl Service {
     &self,
     worker: Worker,
     destination: Destination,
+    batching: &Batching,
     spans: TIntoIterator,
+    interval: Option<chrono::Duration>,
   ) -> Result<
     impl Stream<Item = Result<ReadResponse<TSpan>, ServerReadError>>,
     ServerStreamError,
   > {
-    let iter = spans.into_iter();
-    let len = iter.len();
-    let batches = batch_spans(iter, self.batch_threshold);
+    // NOTE: spans the device rejected before would end the stream again
+    let rejected = worker.rejected(&destination).await;
+    let spans = spans
+      .into_iter()
+      .filter(|span| !rejected.iter().any(|rejected| rejected.contains(span)))
+      .collect::<Vec<_>>();
+    let len = spans.len();
+    let (threshold, max_quantity) =
+      self.batch_limits(worker.plan(&destination).await, batching);
+    let batches = batch_spans(spans, threshold, max_quantity, &batching.holes);
     let stream = match worker
-      .stream(destination, batches.clone().into_iter())
+      .stream(destination, batches.clone(), interval)
       .await
     {
       Ok(stream) => stream,
+      Err(StreamError::Overloaded) => {
+        return Err(ServerStreamError::Overloaded)
+      }
       Err(error) => return Err(ServerStreamError::ServerFailed(error.into())),
     };
     let stream = stream.map(move |result| {
@@ -431,6 +656,21 @@ impl Service {
     Ok(stream)
   }
 
+  fn batch_limits(
+    &self,
+    plan: Option<Plan>,
+    batching: &Batching,
+  ) -> (u16, u16) {
+    let max_quantity = batching.max_quantity.unwrap_or(self.batch_max_quantity);
+    match plan {
+      Some(plan) => (
+        plan.threshold.min(self.batch_threshold),
+        plan.max_quantity.min(max_quantity),
+      ),
+      None => (self.batch_threshold, max_quantity),
+    }
+  }
+
   fn parse_worker_read_response<
     TSpan: Span,
     TSpanParser: Span + SpanParser<TSpan>,
@@ -446,6 +686,15 @@ impl Service {
         super::worker::SendError::FailedToConnect(error) => {
           return Err(ServerReadError::FailedToConnect(error))
         }
+        super::worker::SendError::Exception(exception) => {
+          return Err(ServerReadError::Exception(exception))
+        }
+        super::worker::SendError::Overloaded => {
+          return Err(ServerReadError::Overloaded)
+        }
+        super::worker::SendError::DeadlineExceeded => {
+          return Err(ServerReadError::DeadlineExceeded)
+        }
         super::worker::SendError::ChannelDisconnected(error) => {
           return Err(ServerReadError::ServerFailed(error))
         }
@@ -453,7 +702,7 @@ impl Service {
     };
 
     let mut response = Vec::with_capacity(len);
-    for (parser, data) in batches.into_iter().zip(data.into_iter()) {
+    for (parser, data) in batches.into_iter().zip(data) {
       let mut parsed =
         match parser.parse_with_timestamp(data.inner, data.timestamp) {
           Ok(parsed) => parsed,
@@ -474,6 +723,15 @@ impl Service {
         super::worker::SendError::FailedToConnect(error) => {
           return Err(ServerWriteError::FailedToConnect(error))
         }
+        super::worker::SendError::Exception(exception) => {
+          return Err(ServerWriteError::Exception(exception))
+        }
+        super::worker::SendError::Overloaded => {
+          return Err(ServerWriteError::Overloaded)
+        }
+        super::worker::SendError::DeadlineExceeded => {
+          return Err(ServerWriteError::DeadlineExceeded)
+        }
         super::worker::SendError::ChannelDisconnected(error) => {
           return Err(ServerWriteError::ServerFailed(error))
         }
@@ -483,17 +741,45 @@ impl Service {
     Ok(data.iter().map(|entry| entry.timestamp).collect::<Vec<_>>())
   }
 
-  async fn get_server(&self, destination: Destination) -> Server {
+  async fn get_server(&self, destination: &Destination) -> Server {
+    let gateway = match &destination.transport {
+      Transport::Tcp(address) | Transport::RtuOverTcp(address) => self
+        .gateways
+        .iter()
+        .find(|gateway| gateway.matches(*address)),
+      Transport::Serial(_) => None,
+    };
+    // NOTE: each worker holds its own session to the gateway so sessions
+    // only get pooled for tcp framing without a delay because rtu puts every
+    // session on the same bus and the delay is kept per session
+    let connections = match (&destination.transport, gateway) {
+      (Transport::Tcp(_), Some(gateway)) if gateway.delay.is_none() => {
+        gateway.connections.unwrap_or(1).max(1)
+      }
+      _ => 1,
+    };
     let mut workers = self.servers.clone().lock_owned().await;
     let worker = workers
-      .entry(destination.address)
+      .entry(destination.transport.clone())
       .or_insert_with(|| Server {
-        worker: Worker::new(
-          self.read_timeout,
-          self.termination_timeout,
-          self.congestion_backoff,
-          self.partial_retries,
-        ),
+        workers: (0..connections)
+          .map(|_| {
+            Worker::new(
+              gateway
+                .and_then(|gateway| gateway.read_timeout)
+                .unwrap_or(self.read_timeout),
+              self.termination_timeout,
+              self.congestion_backoff,
+              self.partial_retries,
+              self.adaptive_batching.then_some(Plan {
+                threshold: self.batch_threshold,
+                max_quantity: self.batch_max_quantity,
+              }),
+              gateway.and_then(|gateway| gateway.delay),
+              self.queue,
+            )
+          })
+          .collect(),
       })
       .clone();
     worker
@@ -508,11 +794,57 @@ impl Service {
 
 #[derive(Clone, Debug)]
 struct Server {
-  worker: Worker,
+  workers: Vec<Worker>,
+}
+
+impl Server {
+  // NOTE: a unit id always maps to the same worker so its requests stay
+  // ordered while different unit ids get pipelined over separate sessions
+  fn worker_for(&self, destination: &Destination) -> Worker {
+    let index = destination
+      .slave
+      .map(usize::from)
+      .and_then(|slave| slave.checked_rem(self.workers.len()))
+      .unwrap_or(0);
+    #[allow(clippy::unwrap_used)] // NOTE: servers always have a worker
+    self
+      .workers
+      .get(index)
+      .or_else(|| self.workers.first())
+      .cloned()
+      .unwrap()
+  }
+
+  async fn totals(&self) -> HashMap<Destination, super::metrics::Counters> {
+    let mut totals = HashMap::new();
+    for worker in self.workers.iter() {
+      totals.extend(worker.totals().await);
+    }
+    totals
+  }
+
+  fn drains(&self) -> u64 {
+    self
+      .workers
+      .iter()
+      .map(Worker::drains)
+      .fold(0u64, u64::saturating_add)
+  }
+
+  async fn terminate(&self) -> Result<(), TerminateError> {
+    let mut result = Ok(());
+    for worker in self.workers.iter() {
+      if let Err(error) = worker.terminate().await {
+        result = Err(error);
+      }
+    }
+    result
+  }
 }
 
 #[derive(Clone, Debug)]
 struct Device {
   worker: Worker,
   destination: Destination,
+  batching: Batching,
 }
@@ -3,21 +3,23 @@ use std::fmt::Debug;
 use either::Either;
 use tokio_modbus::{Address, Quantity};
 
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum RegisterTable {
+  Holding,
+  Input,
+  Coil,
+  Discrete,
+}
+
 pub(crate) trait Span {
+  fn table(&self) -> RegisterTable;
+
   fn address(&self) -> Address;
 
   fn quantity(&self) -> Quantity;
 }
 
 pub(crate) trait SpanParser<TParsed: Span> {
-  fn parse<TIterator, TIntoIterator>(
-    &self,
-    data: TIntoIterator,
-  ) -> anyhow::Result<TParsed>
-  where
-    TIterator: DoubleEndedIterator<Item = u16>,
-    TIntoIterator: IntoIterator<Item = u16, IntoIter = TIterator>;
-
   fn parse_with_timestamp<TIterator, TIntoIterator>(
     &self,
     data: TIntoIterator,
@@ -30,11 +32,27 @@ pub(crate) trait SpanParser<TParsed: Span> {
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub(crate) struct SimpleSpan {
+  pub(crate) table: RegisterTable,
   pub(crate) address: u16,
   pub(crate) quantity: u16,
 }
 
+impl SimpleSpan {
+  pub(crate) fn contains<TSpan: Span>(&self, span: &TSpan) -> bool {
+    let end = u32::from(self.address).saturating_add(u32::from(self.quantity));
+    let span_end =
+      u32::from(span.address()).saturating_add(u32::from(span.quantity()));
+    self.table == span.table()
+      && self.address <= span.address()
+      && span_end <= end
+  }
+}
+
 impl Span for SimpleSpan {
+  fn table(&self) -> RegisterTable {
+    self.table
+  }
+
   fn address(&self) -> Address {
     self.address
   }
@@ -45,6 +63,13 @@ impl Span for SimpleSpan {
 }
 
 impl<TLeftSpan: Span, TRightSpan: Span> Span for Either<TLeftSpan, TRightSpan> {
+  fn table(&self) -> RegisterTable {
+    match self {
+      Either::Left(span) => span.table(),
+      Either::Right(span) => span.table(),
+    }
+  }
+
   fn address(&self) -> Address {
     match self {
       Either::Left(span) => span.address(),
@@ -68,20 +93,6 @@ impl<
   > SpanParser<Either<TLeftSpan, TRightSpan>>
   for Either<TLeftSpanParser, TRightSpanParser>
 {
-  fn parse<TIterator, TIntoIterator>(
-    &self,
-    data: TIntoIterator,
-  ) -> anyhow::Result<Either<TLeftSpan, TRightSpan>>
-  where
-    TIterator: DoubleEndedIterator<Item = u16>,
-    TIntoIterator: IntoIterator<Item = u16, IntoIter = TIterator>,
-  {
-    match self {
-      Either::Left(parser) => Ok(Either::Left(parser.parse(data)?)),
-      Either::Right(parser) => Ok(Either::Right(parser.parse(data)?)),
-    }
-  }
-
   fn parse_with_timestamp<TIterator, TIntoIterator>(
     &self,
     data: TIntoIterator,
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::ops::IndexMut;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use either::Either;
@@ -8,17 +9,36 @@ use futures::Stream;
 use futures_time::future::FutureExt;
 use tokio::sync::Mutex;
 
+use super::batch::{Batch, Plan};
 use super::connection::*;
+use super::identification::Identification;
+use super::metrics::Counters;
 use super::record::{Record, SimpleRecord};
 use super::span::{SimpleSpan, Span};
 
 // NOTE: discovery Read(Custom { kind: Other, error: ExceptionResponse { function: 3, exception: IllegalDataAddress } })
-// NOTE: timeout clog Read(Custom { kind: InvalidData, error: \"Invalid response header: expected/request = Header { transaction_id: 0, unit_id: 255 }, actual/response = Header { transaction_id: 0, unit_id: 2 }\" })
+// NOTE: timeout clog Read(Custom { kind: InvalidData, error: \"Failed draining stale responses\" })
 // NOTE: timeout Timeout(Custom { kind: TimedOut, error: \"future timed out\" })
 
+// NOTE: consecutive IllegalDataAddress failures of a batch before the worker
+// tries reading its inner spans one by one
+const SPLIT_AFTER_FAILURES: u32 = 2;
+
+// NOTE: consecutive clean loops of a destination before its plan grows
+const GROW_AFTER_LOOPS: u32 = 100;
+
+// NOTE: quantity added to the plan of a destination when it grows
+const GROW_QUANTITY_STEP: u16 = 8;
+
 // TODO: shorten this thing - 1k lines is insane
 // OPTIMIZE: remove copying when reading
-// OPTIMIZE: check bounded channel length - maybe config?
+
+// NOTE: pending request limit and one-shot request lifetime of a worker
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Queue {
+  pub(crate) capacity: usize,
+  pub(crate) deadline: chrono::Duration,
+}
 
 #[derive(Debug, Clone)]
 pub(crate) struct ReadResponseEntry {
@@ -40,6 +60,8 @@ pub(crate) type WriteResponse = Vec<WriteResponseEntry>;
 pub(crate) struct Worker {
   sender: RequestSender,
   handle: Arc<Mutex<Option<TaskHandle>>>,
+  shared: Shared,
+  deadline: chrono::Duration,
   termination_timeout: futures_time::time::Duration,
 }
 
@@ -48,12 +70,39 @@ pub(crate) enum SendError {
   #[error("Failed to connect")]
   FailedToConnect(#[from] ConnectError),
 
+  #[error("Server responded with exception {0:?}")]
+  Exception(Exception),
+
+  #[error("Worker queue is full")]
+  Overloaded,
+
+  #[error("Request deadline passed before it could be finished")]
+  DeadlineExceeded,
+
+  #[error("Channel was disconnected before the request could be finished")]
+  ChannelDisconnected(anyhow::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum IdentifyError {
+  #[error("Failed identifying")]
+  Read(#[from] ReadError),
+
+  #[error("Worker queue is full")]
+  Overloaded,
+
+  #[error("Request deadline passed before it could be finished")]
+  DeadlineExceeded,
+
   #[error("Channel was disconnected before the request could be finished")]
   ChannelDisconnected(anyhow::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum StreamError {
+  #[error("Worker queue is full")]
+  Overloaded,
+
   #[error("Channel was disconnected before the request could be finished")]
   ChannelDisconnected(anyhow::Error),
 }
@@ -76,14 +125,27 @@ impl Worker {
     termination_timeout: chrono::Duration,
     congestion_backoff: chrono::Duration,
     partial_retries: u32,
+    plan: Option<Plan>,
+    delay: Option<chrono::Duration>,
+    queue: Queue,
   ) -> Self {
-    let (sender, receiver) = flume::unbounded();
-    let task =
-      Task::new(read_timeout, receiver, congestion_backoff, partial_retries);
+    let (sender, receiver) = flume::bounded(queue.capacity.max(1));
+    let shared = Shared::default();
+    let task = Task::new(
+      read_timeout,
+      receiver,
+      congestion_backoff,
+      partial_retries,
+      shared.clone(),
+      plan,
+      delay,
+    );
     let handle = tokio::spawn(task.execute());
     Self {
       sender,
       handle: Arc::new(Mutex::new(Some(handle))),
+      shared,
+      deadline: queue.deadline,
       termination_timeout: futures_time::time::Duration::from_millis(
         termination_timeout.num_milliseconds() as u64,
       ),
@@ -92,26 +154,77 @@ impl Worker {
 }
 
 impl Worker {
+  pub(crate) async fn plan(&self, destination: &Destination) -> Option<Plan> {
+    let plans = self.shared.plans.clone().lock_owned().await;
+    plans.get(destination).cloned()
+  }
+
+  pub(crate) async fn seed(&self, destination: Destination, plan: Plan) {
+    let mut plans = self.shared.plans.clone().lock_owned().await;
+    plans.entry(destination).or_insert(plan);
+  }
+
+  pub(crate) async fn splits(
+    &self,
+    destination: &Destination,
+  ) -> HashSet<SimpleSpan> {
+    let splits = self.shared.splits.clone().lock_owned().await;
+    splits.get(destination).cloned().unwrap_or_default()
+  }
+
+  pub(crate) async fn seed_splits<
+    TIntoIterator: IntoIterator<Item = SimpleSpan>,
+  >(
+    &self,
+    destination: Destination,
+    learned: TIntoIterator,
+  ) {
+    let mut splits = self.shared.splits.clone().lock_owned().await;
+    splits.entry(destination).or_default().extend(learned);
+  }
+
+  // NOTE: spans the device answered with a rejected class exception
+  pub(crate) async fn rejected(
+    &self,
+    destination: &Destination,
+  ) -> HashSet<SimpleSpan> {
+    let rejected = self.shared.rejected.clone().lock_owned().await;
+    rejected.get(destination).cloned().unwrap_or_default()
+  }
+
+  pub(crate) async fn totals(&self) -> HashMap<Destination, Counters> {
+    let totals = self.shared.totals.clone().lock_owned().await;
+    totals.clone()
+  }
+
+  pub(crate) fn drains(&self) -> u64 {
+    self.shared.drains.load(Ordering::Relaxed)
+  }
+
   pub(crate) async fn read<
     TSpan: Span,
-    TIntoIterator: IntoIterator<Item = TSpan>,
+    TBatch: Borrow<Batch<TSpan>>,
+    TIntoIterator: IntoIterator<Item = TBatch>,
   >(
     &self,
     destination: Destination,
     spans: TIntoIterator,
   ) -> Result<ReadResponse, SendError> {
     let (sender, receiver) = flume::bounded(1);
-    if let Err(error) = self
-      .sender
-      .send_async(TaskRequest::Read(ReadTaskRequest::new(
+    if let Err(error) =
+      self.sender.try_send(TaskRequest::Read(ReadTaskRequest::new(
         destination,
         spans,
-        ReadRequestKind::Read,
+        ReadRequestKind::Read(self.deadline()),
         sender,
       )))
-      .await
     {
-      return Err(SendError::ChannelDisconnected(error.into()));
+      return Err(match error {
+        flume::TrySendError::Full(_) => SendError::Overloaded,
+        flume::TrySendError::Disconnected(_) => {
+          SendError::ChannelDisconnected(error.into())
+        }
+      });
     };
     let response = match receiver.recv_async().await {
       Ok(response) => response,
@@ -121,6 +234,37 @@ impl Worker {
     Ok(response)
   }
 
+  pub(crate) async fn identify(
+    &self,
+    destination: Destination,
+  ) -> Result<Identification, IdentifyError> {
+    let (sender, receiver) = flume::bounded(1);
+    if let Err(error) =
+      self
+        .sender
+        .try_send(TaskRequest::Identify(IdentifyTaskRequest {
+          destination,
+          deadline: self.deadline(),
+          sender,
+        }))
+    {
+      return Err(match error {
+        flume::TrySendError::Full(_) => IdentifyError::Overloaded,
+        flume::TrySendError::Disconnected(_) => {
+          IdentifyError::ChannelDisconnected(error.into())
+        }
+      });
+    };
+    let response = match receiver.recv_async().await {
+      Ok(response) => response,
+      Err(error) => {
+        return Err(IdentifyError::ChannelDisconnected(error.into()))
+      }
+    }?;
+
+    Ok(response)
+  }
+
   pub(crate) async fn write<
     TRecord: Record,
     TIntoIterator: IntoIterator<Item = TRecord>,
@@ -130,16 +274,22 @@ impl Worker {
     records: TIntoIterator,
   ) -> Result<WriteResponse, SendError> {
     let (sender, receiver) = flume::bounded(1);
-    if let Err(error) = self
-      .sender
-      .send_async(TaskRequest::Write(WriteTaskRequest::new(
-        destination,
-        records,
-        sender,
-      )))
-      .await
+    if let Err(error) =
+      self
+        .sender
+        .try_send(TaskRequest::Write(WriteTaskRequest::new(
+          destination,
+          records,
+          self.deadline(),
+          sender,
+        )))
     {
-      return Err(SendError::ChannelDisconnected(error.into()));
+      return Err(match error {
+        flume::TrySendError::Full(_) => SendError::Overloaded,
+        flume::TrySendError::Disconnected(_) => {
+          SendError::ChannelDisconnected(error.into())
+        }
+      });
     };
     let response = match receiver.recv_async().await {
       Ok(response) => response,
@@ -151,32 +301,41 @@ impl Worker {
 
   pub(crate) async fn stream<
     TSpan: Span,
-    TIntoIterator: IntoIterator<Item = TSpan>,
+    TBatch: Borrow<Batch<TSpan>>,
+    TIntoIterator: IntoIterator<Item = TBatch>,
   >(
     &self,
     destination: Destination,
     spans: TIntoIterator,
+    interval: Option<chrono::Duration>,
   ) -> Result<
     impl Stream<Item = Result<ReadResponse, SendError>> + Send + Sync,
     StreamError,
   > {
     let (sender, receiver) = flume::bounded(1024);
-    if let Err(error) = self
-      .sender
-      .send_async(TaskRequest::Read(ReadTaskRequest::new(
+    if let Err(error) =
+      self.sender.try_send(TaskRequest::Read(ReadTaskRequest::new(
         destination,
         spans,
-        ReadRequestKind::Stream,
+        ReadRequestKind::Stream(interval),
         sender,
       )))
-      .await
     {
-      return Err(StreamError::ChannelDisconnected(error.into()));
+      return Err(match error {
+        flume::TrySendError::Full(_) => StreamError::Overloaded,
+        flume::TrySendError::Disconnected(_) => {
+          StreamError::ChannelDisconnected(error.into())
+        }
+      });
     };
     let stream = receiver.into_stream();
     Ok(stream)
   }
 
+  fn deadline(&self) -> Deadline {
+    chrono::Utc::now().checked_add_signed(self.deadline)
+  }
+
   pub(crate) async fn terminate(&self) -> Result<(), TerminateError> {
     let result = self.sender.send_async(TaskRequest::Terminate).await;
 
@@ -205,16 +364,30 @@ impl Worker {
 
 type TaskHandle = tokio::task::JoinHandle<()>;
 
+// NOTE: state the task updates and the worker reads
+#[derive(Debug, Clone, Default)]
+struct Shared {
+  plans: Arc<Mutex<HashMap<Destination, Plan>>>,
+  splits: Arc<Mutex<HashMap<Destination, HashSet<SimpleSpan>>>>,
+  rejected: Arc<Mutex<HashMap<Destination, HashSet<SimpleSpan>>>>,
+  totals: Arc<Mutex<HashMap<Destination, Counters>>>,
+  // NOTE: handed to every connection so the count survives reconnects
+  drains: Arc<AtomicU64>,
+}
+
+// NOTE: streams never expire so they carry no deadline
+type Deadline = Option<chrono::DateTime<chrono::Utc>>;
+
 #[derive(Clone, Debug)]
 enum ReadRequestKind {
-  Read,
-  Stream,
+  Read(Deadline),
+  Stream(Option<chrono::Duration>),
 }
 
 #[derive(Clone, Debug)]
 struct ReadTaskRequest {
   destination: Destination,
-  spans: Vec<SimpleSpan>,
+  spans: Vec<Batch<SimpleSpan>>,
   kind: ReadRequestKind,
   sender: ReadResponseSender,
 }
@@ -223,18 +396,31 @@ struct ReadTaskRequest {
 struct WriteTaskRequest {
   destination: Destination,
   records: Vec<SimpleRecord>,
+  deadline: Deadline,
   sender: WriteResponseSender,
 }
 
+#[derive(Clone, Debug)]
+struct IdentifyTaskRequest {
+  destination: Destination,
+  deadline: Deadline,
+  sender: IdentifyResponseSender,
+}
+
 #[derive(Clone, Debug)]
 enum TaskRequest {
   Read(ReadTaskRequest),
   Write(WriteTaskRequest),
+  Identify(IdentifyTaskRequest),
   Terminate,
 }
 
 impl ReadTaskRequest {
-  fn new<TSpan: Span, TIntoIterator: IntoIterator<Item = TSpan>>(
+  fn new<
+    TSpan: Span,
+    TBatch: Borrow<Batch<TSpan>>,
+    TIntoIterator: IntoIterator<Item = TBatch>,
+  >(
     destination: Destination,
     spans: TIntoIterator,
     kind: ReadRequestKind,
@@ -244,9 +430,22 @@ impl ReadTaskRequest {
       destination,
       spans: spans
         .into_iter()
-        .map(|span| SimpleSpan {
-          address: span.address(),
-          quantity: span.quantity(),
+        .map(|batch| {
+          let batch = batch.borrow();
+          Batch::<SimpleSpan> {
+            table: batch.table,
+            address: batch.address,
+            quantity: batch.quantity,
+            inner: batch
+              .inner
+              .iter()
+              .map(|span| SimpleSpan {
+                table: span.table(),
+                address: span.address(),
+                quantity: span.quantity(),
+              })
+              .collect::<Vec<_>>(),
+          }
         })
         .collect::<Vec<_>>(),
       kind,
@@ -259,6 +458,7 @@ impl WriteTaskRequest {
   fn new<TRecord: Record, TIntoIterator: IntoIterator<Item = TRecord>>(
     destination: Destination,
     records: TIntoIterator,
+    deadline: Deadline,
     sender: WriteResponseSender,
   ) -> Self {
     Self {
@@ -266,10 +466,12 @@ impl WriteTaskRequest {
       records: records
         .into_iter()
         .map(|record| SimpleRecord {
+          table: record.table(),
           address: record.address(),
           values: record.values().collect::<Vec<_>>(),
         })
         .collect::<Vec<_>>(),
+      deadline,
       sender,
     }
   }
@@ -279,6 +481,8 @@ type RequestSender = flume::Sender<TaskRequest>;
 type RequestReceiver = flume::Receiver<TaskRequest>;
 type ReadResponseSender = flume::Sender<Result<ReadResponse, SendError>>;
 type WriteResponseSender = flume::Sender<Result<WriteResponse, SendError>>;
+type IdentifyResponseSender =
+  flume::Sender<Result<Identification, IdentifyError>>;
 
 type Id = uuid::Uuid;
 
@@ -298,6 +502,7 @@ struct WritePartial {
 struct WriteRequestStorage {
   id: Id,
   sender: WriteResponseSender,
+  deadline: Deadline,
   destination: Destination,
   records: Vec<SimpleRecord>,
   partial: WritePartial,
@@ -307,21 +512,76 @@ struct WriteRequestStorage {
 struct ReadRequestStorage {
   id: Id,
   sender: ReadResponseSender,
+  deadline: Deadline,
   destination: Destination,
-  spans: Vec<SimpleSpan>,
+  spans: Vec<Batch<SimpleSpan>>,
   partial: ReadPartial,
   generation: u64,
+  // NOTE: streams without an interval are read as fast as the bus allows
+  interval: Option<chrono::Duration>,
+  due: Option<tokio::time::Instant>,
+}
+
+impl ReadRequestStorage {
+  fn is_due(&self, now: tokio::time::Instant) -> bool {
+    self.due.is_none_or(|due| due <= now)
+  }
+
+  // NOTE: pacing follows the previous due time so reads don't drift
+  fn next_due(&self) -> Option<tokio::time::Instant> {
+    let interval = self.interval?.to_std().ok()?;
+    let now = tokio::time::Instant::now();
+    let due = self.due.unwrap_or(now).checked_add(interval)?;
+    Some(due.max(now))
+  }
+}
+
+#[derive(Debug, Default)]
+struct Splits {
+  learned: HashSet<SimpleSpan>,
+  failures: HashMap<SimpleSpan, u32>,
+  rejected: HashSet<SimpleSpan>,
+}
+
+// NOTE: span the device rejected and the exception it rejected it with
+#[derive(Debug, Clone, Copy)]
+struct Rejection {
+  span: SimpleSpan,
+  exception: Exception,
+}
+
+impl Splits {
+  // NOTE: learned splits go through shared state so they can be persisted
+  // and seeded back after a restart
+  async fn sync(&mut self, shared: &Shared, destination: &Destination) {
+    let mut splits = shared.splits.clone().lock_owned().await;
+    let learned = splits.entry(destination.clone()).or_default();
+    self.learned.extend(learned.iter().copied());
+    learned.extend(self.learned.iter().copied());
+
+    let mut rejected = shared.rejected.clone().lock_owned().await;
+    rejected
+      .entry(destination.clone())
+      .or_default()
+      .extend(self.rejected.iter().copied());
+  }
 }
 
 #[derive(Debug)]
 struct Task {
-  connections: HashMap<SocketAddr, Connection>,
+  connections: HashMap<Transport, Connection>,
+  splits: HashMap<Destination, Splits>,
+  shared: Shared,
+  plan: Option<Plan>,
+  streaks: HashMap<Destination, u32>,
   receiver: RequestReceiver,
   reads: Vec<ReadRequestStorage>,
   writes: Vec<WriteRequestStorage>,
+  identifications: Vec<IdentifyTaskRequest>,
   streams: Vec<ReadRequestStorage>,
   terminate: bool,
   timeout: chrono::Duration,
+  delay: Option<chrono::Duration>,
   congestion_backoff: tokio::time::Duration,
   partial_retries: u32,
 }
@@ -332,15 +592,24 @@ impl Task {
     receiver: RequestReceiver,
     congestion_backoff: chrono::Duration,
     partial_retries: u32,
+    shared: Shared,
+    plan: Option<Plan>,
+    delay: Option<chrono::Duration>,
   ) -> Self {
     Self {
       connections: HashMap::new(),
+      splits: HashMap::new(),
+      shared,
+      plan,
+      streaks: HashMap::new(),
       receiver,
       writes: Vec::new(),
       reads: Vec::new(),
+      identifications: Vec::new(),
       streams: Vec::new(),
       terminate: false,
       timeout: read_timeout,
+      delay,
       congestion_backoff: tokio::time::Duration::from_millis(
         congestion_backoff.num_milliseconds() as u64,
       ),
@@ -350,50 +619,154 @@ impl Task {
 
   pub(crate) async fn execute(mut self) {
     loop {
+      let now = tokio::time::Instant::now();
       if self.reads.is_empty()
-        && self.streams.is_empty()
         && self.writes.is_empty()
+        && self.identifications.is_empty()
+        && !self.streams.iter().any(|stream| stream.is_due(now))
       {
-        if let Err(error) = self.recv_async_new_request().await {
+        // NOTE: paced streams wake the task when the earliest one is due
+        let result =
+          match self.streams.iter().filter_map(|stream| stream.due).min() {
+            Some(due) => {
+              match tokio::time::timeout_at(due, self.recv_async_new_request())
+                .await
+              {
+                Ok(result) => result,
+                Err(_) => Ok(()),
+              }
+            }
+            None => self.recv_async_new_request().await,
+          };
+        if let Err(error) = result {
           match error {
             flume::RecvError::Disconnected => return,
           }
         }
       }
 
-      loop {
-        if let Err(error) = self.try_recv_new_request() {
-          match error {
-            flume::TryRecvError::Empty => break,
-            flume::TryRecvError::Disconnected => return,
-          }
-        }
+      if let Err(flume::TryRecvError::Disconnected) =
+        self.try_recv_new_requests()
+      {
+        return;
       }
 
       let mut metrics = Metrics::new();
 
-      self.process_reads(&mut metrics).await;
+      // NOTE: writes go before one-shot reads which go before streams
       self.process_writes(&mut metrics).await;
+      self.process_reads(&mut metrics).await;
+      self.process_identifications().await;
 
       if self.terminate {
         if !self.streams.is_empty() {
           self.streams = Vec::new();
         }
-      } else if let Some(generation) =
-        self.streams.iter().map(|stream| stream.generation).min()
+      } else if let Some(generation) = self
+        .streams
+        .iter()
+        .filter(|stream| stream.is_due(tokio::time::Instant::now()))
+        .map(|stream| stream.generation)
+        .min()
       {
         self.process_streams(&mut metrics, generation).await;
       }
 
+      self.record(&metrics).await;
+
       if !self.terminate {
         tracing::trace!("{:#?}", metrics);
+        self.adapt(&metrics).await;
+      }
+    }
+  }
+
+  // NOTE: loop metrics are folded into totals that outlive the loop
+  async fn record(&mut self, metrics: &Metrics) {
+    let mut totals = self.shared.totals.clone().lock_owned().await;
+    for (destination, reads) in metrics.reads.iter() {
+      let counters = totals.entry(destination.clone()).or_default();
+      for read in reads {
+        counters.record_read(read.error, read.congested, read.time);
+      }
+    }
+    for (destination, writes) in metrics.writes.iter() {
+      let counters = totals.entry(destination.clone()).or_default();
+      for write in writes {
+        counters.record_write(write.error, write.time);
+      }
+    }
+  }
+
+  async fn adapt(&mut self, metrics: &Metrics) {
+    let initial = match self.plan {
+      Some(plan) => plan,
+      None => return,
+    };
+
+    let mut changed = Vec::new();
+    {
+      let mut plans = self.shared.plans.clone().lock_owned().await;
+      for (destination, reads) in metrics.reads.iter() {
+        if reads.is_empty() {
+          continue;
+        }
+
+        let plan = plans.entry(destination.clone()).or_insert(initial);
+        let streak = self.streaks.entry(destination.clone()).or_insert(0);
+        let slow = reads.iter().filter_map(|read| read.time).any(|time| {
+          time.num_milliseconds() > self.timeout.num_milliseconds() / 2
+        });
+        let adapted = if reads.iter().any(|read| read.congested) {
+          *streak = 0;
+          plan.shrink()
+        } else if slow || reads.iter().any(|read| read.error) {
+          *streak = 0;
+          *plan
+        } else {
+          *streak = streak.saturating_add(1);
+          if *streak < GROW_AFTER_LOOPS {
+            *plan
+          } else {
+            *streak = 0;
+            plan.grow(GROW_QUANTITY_STEP, initial)
+          }
+        };
+
+        if adapted != *plan {
+          tracing::debug!(
+            "Adapted plan of {:?} from {:?} to {:?}",
+            destination,
+            plan,
+            adapted
+          );
+          *plan = adapted;
+          changed.push(destination.clone());
+        }
       }
     }
+
+    // NOTE: dropping the stream lets the caller restart it with the new plan
+    self
+      .streams
+      .retain(|stream| !changed.contains(&stream.destination));
+  }
+
+  // NOTE: lets writes that arrived in the meantime jump ahead of reads
+  async fn preempt_writes(&mut self, metrics: &mut Metrics) {
+    let received = self.try_recv_new_requests().unwrap_or(0);
+    if received > 0 && !self.writes.is_empty() {
+      self.process_writes(metrics).await;
+    }
   }
 
   async fn process_reads(&mut self, metrics: &mut Metrics) {
     let mut reads_to_remove = Vec::new();
     for index in 0..self.reads.len() {
+      if index > 0 {
+        self.preempt_writes(metrics).await;
+      }
+
       let read = self.reads.index_mut(index);
       if read.sender.is_disconnected() {
         tracing::trace! {
@@ -404,17 +777,35 @@ impl Task {
         continue;
       }
 
+      if is_expired(read.deadline) {
+        if let Err(error) =
+          read.sender.try_send(Err(SendError::DeadlineExceeded))
+        {
+          // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+          tracing::trace!(
+            "Failed sending read deadline to {:?} {}",
+            read.destination,
+            error,
+          )
+        }
+
+        reads_to_remove.push(read.id);
+        continue;
+      }
+
       let connection = match Self::attempt_connection(
         &mut self.connections,
         &read.destination,
         Either::Left(&read.sender),
+        self.delay,
+        &self.shared.drains,
       )
       .await
       {
         ConnectionAttempt::Existing(connection) => connection,
         ConnectionAttempt::New(connection) => self
           .connections
-          .entry(read.destination.address)
+          .entry(read.destination.transport.clone())
           .or_insert(connection),
         ConnectionAttempt::Fail => {
           reads_to_remove.push(read.id);
@@ -422,19 +813,37 @@ impl Task {
         }
       };
 
-      match Self::read(
+      let splits = self.splits.entry(read.destination.clone()).or_default();
+      splits.sync(&self.shared, &read.destination).await;
+      let result = Self::read(
         read,
         metrics,
         connection,
+        splits,
         self.timeout,
         self.congestion_backoff,
       )
-      .await
-      {
-        Either::Left(partial) => {
+      .await;
+      splits.sync(&self.shared, &read.destination).await;
+      match result {
+        Ok(Either::Left(partial)) => {
           read.partial = partial;
         }
-        Either::Right(response) => {
+        Err(exception) => {
+          if let Err(error) =
+            read.sender.try_send(Err(SendError::Exception(exception)))
+          {
+            // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+            tracing::trace!(
+              "Failed sending read exception to {:?} {}",
+              read.destination,
+              error,
+            )
+          }
+
+          reads_to_remove.push(read.id);
+        }
+        Ok(Either::Right(response)) => {
           if let Err(error) = read.sender.try_send(Ok(response)) {
             // NOTE: error -> trace because this should fail when we already cancelled the future from caller
             tracing::trace!(
@@ -450,7 +859,7 @@ impl Task {
     }
     self
       .reads
-      .retain(|read| !reads_to_remove.iter().any(|id| *id == read.id));
+      .retain(|read| !reads_to_remove.contains(&read.id));
 
     tracing::trace!(
       "Removed reads {:?} - retained {:?}",
@@ -476,17 +885,35 @@ impl Task {
         continue;
       }
 
+      if is_expired(write.deadline) {
+        if let Err(error) =
+          write.sender.try_send(Err(SendError::DeadlineExceeded))
+        {
+          // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+          tracing::trace!(
+            "Failed sending write deadline to {:?} {}",
+            write.destination,
+            error,
+          )
+        }
+
+        writes_to_remove.push(write.id);
+        continue;
+      }
+
       let connection = match Self::attempt_connection(
         &mut self.connections,
         &write.destination,
         Either::Right(&write.sender),
+        self.delay,
+        &self.shared.drains,
       )
       .await
       {
         ConnectionAttempt::Existing(connection) => connection,
         ConnectionAttempt::New(connection) => self
           .connections
-          .entry(write.destination.address)
+          .entry(write.destination.transport.clone())
           .or_insert(connection),
         ConnectionAttempt::Fail => {
           writes_to_remove.push(write.id);
@@ -522,7 +949,7 @@ impl Task {
     }
     self
       .writes
-      .retain(|write| !writes_to_remove.iter().any(|id| *id == write.id));
+      .retain(|write| !writes_to_remove.contains(&write.id));
 
     tracing::trace!(
       "Removed writes {:?} - retained {:?}",
@@ -535,10 +962,70 @@ impl Task {
     );
   }
 
+  // NOTE: identification is only used during discovery so it is not retried
+  async fn process_identifications(&mut self) {
+    for request in self.identifications.drain(0..) {
+      if request.sender.is_disconnected() {
+        continue;
+      }
+
+      if is_expired(request.deadline) {
+        if let Err(error) = request
+          .sender
+          .try_send(Err(IdentifyError::DeadlineExceeded))
+        {
+          // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+          tracing::trace!(
+            "Failed sending identification deadline to {:?} {}",
+            request.destination,
+            error,
+          )
+        }
+
+        continue;
+      }
+
+      let connection = self
+        .connections
+        .entry(request.destination.transport.clone())
+        .or_insert_with(|| {
+          Connection::new(
+            request.destination.transport.clone(),
+            self.delay,
+            self.shared.drains.clone(),
+          )
+        });
+      let response = connection
+        .identify(request.destination.slave, self.timeout)
+        .await
+        .map_err(IdentifyError::from);
+      if let Err(error) = request.sender.try_send(response) {
+        // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+        tracing::trace!(
+          "Failed sending identification to {:?} {}",
+          request.destination,
+          error,
+        )
+      }
+    }
+  }
+
   async fn process_streams(&mut self, metrics: &mut Metrics, generation: u64) {
     let mut streams_to_remove = Vec::new();
     for index in 0..self.streams.len() {
-      let stream = self.streams.index_mut(index);
+      // NOTE: lets requests that arrived in the meantime jump ahead of streams
+      if index > 0 && self.try_recv_new_requests().unwrap_or(0) > 0 {
+        self.process_writes(metrics).await;
+        self.process_reads(metrics).await;
+      }
+
+      // NOTE: a terminate request received in the meantime clears the streams
+      if self.terminate {
+        break;
+      }
+      let Some(stream) = self.streams.get_mut(index) else {
+        break;
+      };
       if stream.sender.is_disconnected() {
         tracing::trace! {
           "{} stream sender got disconnected",
@@ -548,7 +1035,9 @@ impl Task {
         continue;
       }
 
-      if stream.generation != generation {
+      if stream.generation != generation
+        || !stream.is_due(tokio::time::Instant::now())
+      {
         continue;
       }
 
@@ -558,6 +1047,7 @@ impl Task {
           spans: vec![None; stream.spans.len()],
           retries: 0,
         };
+        stream.due = stream.next_due();
         continue;
       }
 
@@ -565,13 +1055,15 @@ impl Task {
         &mut self.connections,
         &stream.destination,
         Either::Left(&stream.sender),
+        self.delay,
+        &self.shared.drains,
       )
       .await
       {
         ConnectionAttempt::Existing(connection) => connection,
         ConnectionAttempt::New(connection) => self
           .connections
-          .entry(stream.destination.address)
+          .entry(stream.destination.transport.clone())
           .or_insert(connection),
         ConnectionAttempt::Fail => {
           streams_to_remove.push(stream.id);
@@ -579,19 +1071,42 @@ impl Task {
         }
       };
 
-      match Self::read(
+      let splits = self.splits.entry(stream.destination.clone()).or_default();
+      splits.sync(&self.shared, &stream.destination).await;
+      let result = Self::read(
         stream,
         metrics,
         connection,
+        splits,
         self.timeout,
         self.congestion_backoff,
       )
-      .await
-      {
-        Either::Left(partial) => {
+      .await;
+      splits.sync(&self.shared, &stream.destination).await;
+      match result {
+        // NOTE: failed spans get retried at the next due time so a failing
+        // device doesn't get hammered by back to back retries
+        Ok(Either::Left(partial)) => {
           stream.partial = partial;
+          stream.due = stream.next_due();
         }
-        Either::Right(response) => {
+        // NOTE: rejected spans would fail every read so the stream ends and
+        // the caller restarts it without them
+        Err(exception) => {
+          if let Err(error) =
+            stream.sender.try_send(Err(SendError::Exception(exception)))
+          {
+            // NOTE: error -> trace because this should fail when we already cancelled the future from caller
+            tracing::trace!(
+              "Failed sending stream exception to {:?} {}",
+              stream.destination,
+              error,
+            );
+          }
+
+          streams_to_remove.push(stream.id);
+        }
+        Ok(Either::Right(response)) => {
           match stream.sender.try_send(Ok(response)) {
             Ok(()) => {
               stream.partial = ReadPartial {
@@ -599,6 +1114,7 @@ impl Task {
                 retries: 0,
               };
               stream.generation = stream.generation.saturating_add(1);
+              stream.due = stream.next_due();
             }
             Err(error) => {
               // NOTE: error -> trace because this should fail when we already cancelled the future from caller
@@ -616,7 +1132,7 @@ impl Task {
     }
     self
       .streams
-      .retain(|stream| !streams_to_remove.iter().any(|id| *id == stream.id));
+      .retain(|stream| !streams_to_remove.contains(&stream.id));
 
     tracing::trace!(
       "Removed streams {:?} - retained {:?}",
@@ -629,6 +1145,28 @@ impl Task {
     );
   }
 
+  // NOTE: requests past capacity stay in the bounded channel so callers get
+  // rejected as overloaded instead of queueing without limit
+  fn try_recv_new_requests(&mut self) -> Result<usize, flume::TryRecvError> {
+    let capacity = self.receiver.capacity().unwrap_or(usize::MAX);
+    let mut received = 0usize;
+    while self
+      .reads
+      .len()
+      .saturating_add(self.writes.len())
+      .saturating_add(self.identifications.len())
+      < capacity
+    {
+      match self.try_recv_new_request() {
+        Ok(()) => received = received.saturating_add(1),
+        Err(flume::TryRecvError::Empty) => break,
+        Err(error) => return Err(error),
+      }
+    }
+
+    Ok(received)
+  }
+
   #[tracing::instrument(skip_all)]
   fn try_recv_new_request(&mut self) -> Result<(), flume::TryRecvError> {
     match self.receiver.try_recv()? {
@@ -642,12 +1180,17 @@ impl Task {
           self.add_new_write_request(request);
         }
       }
+      TaskRequest::Identify(request) => {
+        if !self.terminate {
+          self.identifications.push(request);
+        }
+      }
       TaskRequest::Terminate => {
         self.terminate = true;
         self.streams = Vec::new();
         tracing::trace!(
           "Terminating {:?}",
-          self.reads.first().map(|read| read.destination.address)
+          self.reads.first().map(|read| &read.destination.transport)
         );
       }
     }
@@ -668,12 +1211,17 @@ impl Task {
           self.add_new_write_request(request);
         }
       }
+      TaskRequest::Identify(request) => {
+        if !self.terminate {
+          self.identifications.push(request);
+        }
+      }
       TaskRequest::Terminate => {
         self.terminate = true;
         self.streams = Vec::new();
         tracing::trace!(
           "Terminating {:?}",
-          self.reads.first().map(|read| read.destination.address)
+          self.reads.first().map(|read| &read.destination.transport)
         );
       }
     }
@@ -696,6 +1244,15 @@ impl Task {
     let storage = ReadRequestStorage {
       id: Id::new_v4(),
       sender,
+      deadline: match kind {
+        ReadRequestKind::Read(deadline) => deadline,
+        ReadRequestKind::Stream(_) => None,
+      },
+      interval: match kind {
+        ReadRequestKind::Read(_) => None,
+        ReadRequestKind::Stream(interval) => interval,
+      },
+      due: None,
       destination,
       spans,
       partial: ReadPartial {
@@ -711,8 +1268,8 @@ impl Task {
     };
 
     match kind {
-      ReadRequestKind::Read => self.reads.push(storage),
-      ReadRequestKind::Stream => self.streams.push(storage),
+      ReadRequestKind::Read(_) => self.reads.push(storage),
+      ReadRequestKind::Stream(_) => self.streams.push(storage),
     };
 
     tracing::trace!("Added read request");
@@ -725,12 +1282,14 @@ impl Task {
     let WriteTaskRequest {
       destination,
       records,
+      deadline,
       sender,
     } = request;
     let records_len = records.len();
     let storage = WriteRequestStorage {
       id: Id::new_v4(),
       sender,
+      deadline,
       destination,
       records,
       partial: WritePartial {
@@ -745,6 +1304,10 @@ impl Task {
   }
 }
 
+fn is_expired(deadline: Deadline) -> bool {
+  deadline.is_some_and(|deadline| chrono::Utc::now() > deadline)
+}
+
 enum ConnectionAttempt<'a> {
   Existing(&'a mut Connection),
   New(Connection),
@@ -754,18 +1317,21 @@ enum ConnectionAttempt<'a> {
 impl Task {
   #[tracing::instrument(skip_all, fields(address = ?destination))]
   async fn attempt_connection<'a>(
-    connections: &'a mut HashMap<SocketAddr, Connection>,
+    connections: &'a mut HashMap<Transport, Connection>,
     destination: &Destination,
     sender: Either<&ReadResponseSender, &WriteResponseSender>,
+    delay: Option<chrono::Duration>,
+    drains: &Arc<AtomicU64>,
   ) -> ConnectionAttempt<'a> {
-    match connections.get_mut(&destination.address) {
+    match connections.get_mut(&destination.transport) {
       Some(connection) => {
         tracing::trace!("Connected to existing connection");
 
         ConnectionAttempt::Existing(connection)
       }
       None => {
-        let mut connection = Connection::new(destination.address);
+        let mut connection =
+          Connection::new(destination.transport.clone(), delay, drains.clone());
         match connection.ensure_connected().await {
           Ok(()) => {
             tracing::trace!("Connected to new connection");
@@ -810,80 +1376,86 @@ impl Task {
     storage: &ReadRequestStorage,
     metrics: &mut Metrics,
     connection: &mut Connection,
+    splits: &mut Splits,
     timeout: chrono::Duration,
     congestion_backoff: tokio::time::Duration,
-  ) -> Either<ReadPartial, ReadResponse> {
+  ) -> Result<Either<ReadPartial, ReadResponse>, Exception> {
     let partial = {
       let mut data = Vec::new();
-      for (span, partial) in storage
-        .spans
-        .iter()
-        .cloned()
-        .zip(storage.partial.spans.iter())
+      for (batch, partial) in
+        storage.spans.iter().zip(storage.partial.spans.iter())
       {
+        let span = SimpleSpan {
+          table: batch.table,
+          address: batch.address,
+          quantity: batch.quantity,
+        };
         let read = match partial {
-          Some(partial) => Some(partial.clone()),
-          None => {
-            if let Err(error) = connection.ensure_connected().await {
-              metrics.reads.entry(storage.destination).or_default().push(
-                ReadMetric {
-                  message: format!(
-                    "Failed connecting span {:?} {:?}",
-                    span, &error
-                  ),
-                  error: true,
-                  span,
-                  time: None,
-                },
-              );
-
-              None
-            } else {
-              let start = chrono::Utc::now();
-              let data = (*connection)
-                .read(storage.destination.slave, span, timeout)
+          Some(partial) => Ok(Some(partial.clone())),
+          None if splits.learned.contains(&span) => {
+            Self::read_split(
+              &storage.destination,
+              metrics,
+              connection,
+              batch,
+              timeout,
+              congestion_backoff,
+            )
+            .await
+          }
+          None => match Self::read_span(
+            &storage.destination,
+            metrics,
+            connection,
+            span,
+            timeout,
+            congestion_backoff,
+          )
+          .await
+          {
+            Ok(data) => {
+              splits.failures.remove(&span);
+
+              Ok(Some(ReadResponseEntry {
+                inner: data,
+                timestamp: chrono::Utc::now(),
+              }))
+            }
+            Err(ReadError::Exception(Exception::IllegalDataAddress))
+              if batch.inner.len() > 1 =>
+            {
+              let failures = splits.failures.entry(span).or_insert(0);
+              *failures = failures.saturating_add(1);
+              if *failures < SPLIT_AFTER_FAILURES {
+                Ok(None)
+              } else {
+                let read = Self::read_split(
+                  &storage.destination,
+                  metrics,
+                  connection,
+                  batch,
+                  timeout,
+                  congestion_backoff,
+                )
                 .await;
-              let end = chrono::Utc::now();
-
-              match data {
-                Ok(data) => {
-                  metrics.reads.entry(storage.destination).or_default().push(
-                    ReadMetric {
-                      message: format!("Successfully read span {:?}", span),
-                      error: false,
-                      span,
-                      time: Some(end.signed_duration_since(start)),
-                    },
-                  );
-
-                  Some(ReadResponseEntry {
-                    inner: data,
-                    timestamp: chrono::Utc::now(),
-                  })
+                if let Ok(Some(_)) = read {
+                  tracing::debug!("Learned split of batch {:?}", span);
+                  splits.failures.remove(&span);
+                  splits.learned.insert(span);
                 }
-                Err(error) => {
-                  metrics.reads.entry(storage.destination).or_default().push(
-                    ReadMetric {
-                      message: format!(
-                        "Failed reading span {:?} {:?}",
-                        span, &error
-                      ),
-                      error: true,
-                      span,
-                      time: Some(end.signed_duration_since(start)),
-                    },
-                  );
 
-                  if let ReadError::Read(io_error) = error {
-                    if io_error.kind() == std::io::ErrorKind::InvalidData {
-                      tokio::time::sleep(congestion_backoff).await;
-                    }
-                  };
-
-                  None
-                }
+                read
               }
             }
+            Err(error) => Self::reject(error, span),
+          },
+        };
+        let read = match read {
+          Ok(read) => read,
+          Err(Rejection { span, exception }) => {
+            tracing::debug!("Rejected span {:?} with {:?}", span, exception);
+            splits.rejected.insert(span);
+            return Err(exception);
           }
         };
 
@@ -895,13 +1467,129 @@ impl Task {
 
     if partial.iter().all(|x| x.is_some()) {
       tracing::trace!("Fully read");
-      Either::Right(partial.iter().flatten().cloned().collect::<Vec<_>>())
+      Ok(Either::Right(
+        partial.iter().flatten().cloned().collect::<Vec<_>>(),
+      ))
     } else {
       tracing::trace!("Partially read");
-      Either::Left(ReadPartial {
+      Ok(Either::Left(ReadPartial {
         spans: partial,
         retries: storage.partial.retries.saturating_add(1),
-      })
+      }))
+    }
+  }
+
+  // NOTE: rejected spans fail the whole request because retrying won't help
+  fn reject(
+    error: ReadError,
+    span: SimpleSpan,
+  ) -> Result<Option<ReadResponseEntry>, Rejection> {
+    match error.exception() {
+      Some(exception) if exception.class() == ExceptionClass::Rejected => {
+        Err(Rejection { span, exception })
+      }
+      _ => Ok(None),
+    }
+  }
+
+  async fn read_split(
+    destination: &Destination,
+    metrics: &mut Metrics,
+    connection: &mut Connection,
+    batch: &Batch<SimpleSpan>,
+    timeout: chrono::Duration,
+    congestion_backoff: tokio::time::Duration,
+  ) -> Result<Option<ReadResponseEntry>, Rejection> {
+    let mut data = vec![0u16; batch.quantity as usize];
+    for span in batch.inner.iter() {
+      let offset = match span.address.checked_sub(batch.address) {
+        Some(offset) => offset as usize,
+        None => return Ok(None),
+      };
+      let values = match Self::read_span(
+        destination,
+        metrics,
+        connection,
+        *span,
+        timeout,
+        congestion_backoff,
+      )
+      .await
+      {
+        Ok(values) => values,
+        Err(error) => return Self::reject(error, *span),
+      };
+      for (target, value) in data.iter_mut().skip(offset).zip(values) {
+        *target = value;
+      }
+    }
+
+    Ok(Some(ReadResponseEntry {
+      inner: data,
+      timestamp: chrono::Utc::now(),
+    }))
+  }
+
+  async fn read_span(
+    destination: &Destination,
+    metrics: &mut Metrics,
+    connection: &mut Connection,
+    span: SimpleSpan,
+    timeout: chrono::Duration,
+    congestion_backoff: tokio::time::Duration,
+  ) -> Result<super::connection::ReadResponse, ReadError> {
+    if let Err(error) = connection.ensure_connected().await {
+      metrics
+        .reads
+        .entry(destination.clone())
+        .or_default()
+        .push(ReadMetric {
+          message: format!("Failed connecting span {:?} {:?}", span, &error),
+          error: true,
+          congested: false,
+          span,
+          time: None,
+        });
+
+      return Err(error.into());
+    }
+
+    let start = chrono::Utc::now();
+    let data = (*connection).read(destination.slave, span, timeout).await;
+    let end = chrono::Utc::now();
+
+    match data {
+      Ok(data) => {
+        metrics.reads.entry(destination.clone()).or_default().push(
+          ReadMetric {
+            message: format!("Successfully read span {:?}", span),
+            error: false,
+            congested: false,
+            span,
+            time: Some(end.signed_duration_since(start)),
+          },
+        );
+
+        Ok(data)
+      }
+      Err(error) => {
+        metrics.reads.entry(destination.clone()).or_default().push(
+          ReadMetric {
+            message: format!("Failed reading span {:?} {:?}", span, &error),
+            error: true,
+            congested: error.is_congestion(),
+            span,
+            time: Some(end.signed_duration_since(start)),
+          },
+        );
+
+        // NOTE: timeouts already waited long enough
+        if error.is_congestion() && !matches!(error, ReadError::Timeout(_)) {
+          tokio::time::sleep(congestion_backoff).await;
+        }
+
+        Err(error)
+      }
     }
   }
 
@@ -925,8 +1613,11 @@ impl Task {
           Some(partial) => Some(partial.clone()),
           None => {
             if let Err(error) = connection.ensure_connected().await {
-              metrics.writes.entry(storage.destination).or_default().push(
-                WriteMetric {
+              metrics
+                .writes
+                .entry(storage.destination.clone())
+                .or_default()
+                .push(WriteMetric {
                   message: format!(
                     "Failed connecting record {:?} {:?}",
                     record, &error
@@ -934,8 +1625,7 @@ impl Task {
                   error: true,
                   record,
                   time: None,
-                },
-              );
+                });
 
               None
             } else {
@@ -947,14 +1637,16 @@ impl Task {
 
               match data {
                 Ok(data) => {
-                  metrics.writes.entry(storage.destination).or_default().push(
-                    WriteMetric {
+                  metrics
+                    .writes
+                    .entry(storage.destination.clone())
+                    .or_default()
+                    .push(WriteMetric {
                       message: format!("Successfully read span {:?}", record),
                       error: false,
                       record,
                       time: Some(end.signed_duration_since(start)),
-                    },
-                  );
+                    });
 
                   Some(WriteResponseEntry {
                     inner: data,
@@ -962,8 +1654,11 @@ impl Task {
                   })
                 }
                 Err(error) => {
-                  metrics.writes.entry(storage.destination).or_default().push(
-                    WriteMetric {
+                  metrics
+                    .writes
+                    .entry(storage.destination.clone())
+                    .or_default()
+                    .push(WriteMetric {
                       message: format!(
                         "Failed reading span {:?} {:?}",
                         record, &error
@@ -971,13 +1666,20 @@ impl Task {
                       error: true,
                       record,
                       time: Some(end.signed_duration_since(start)),
-                    },
-                  );
+                    });
 
-                  if let WriteError::Read(io_error) = error {
-                    if io_error.kind() == std::io::ErrorKind::InvalidData {
+                  match error {
+                    WriteError::Read(io_error)
+                      if io_error.kind() == std::io::ErrorKind::InvalidData =>
+                    {
+                      tokio::time::sleep(congestion_backoff).await;
+                    }
+                    WriteError::Exception(exception)
+                      if exception.class() == ExceptionClass::Busy =>
+                    {
                       tokio::time::sleep(congestion_backoff).await;
                     }
+                    _ => {}
                   };
 
                   None
@@ -1020,6 +1722,7 @@ struct WriteMetric {
 struct ReadMetric {
   message: String,
   error: bool,
+  congested: bool,
   span: SimpleSpan,
   time: Option<chrono::Duration>,
 }
@@ -6,9 +6,12 @@ use tokio::task::JoinHandle;
 
 use crate::*;
 
+pub(crate) const DEFAULT_PORT: u16 = 502;
+
 #[derive(Debug, Clone)]
 pub(crate) struct Service {
   ip_range: IpAddrRange,
+  ports: Vec<u16>,
   timeout: std::time::Duration,
 }
 
@@ -16,6 +19,7 @@ impl service::Service for Service {
   fn new(config: config::Values) -> Self {
     Self {
       ip_range: config.network.ip_range,
+      ports: config.network.ports,
       timeout: std::time::Duration::from_millis(
         config.network.timeout.num_milliseconds() as u64,
       ),
@@ -30,8 +34,8 @@ impl Service {
     let mut matched_ips = Vec::new();
     let ip_scans = self
       .ip_range
-      .map(|ip| {
-        let socket_address = to_socket(ip);
+      .flat_map(|ip| self.ports.iter().map(move |port| to_socket(ip, *port)))
+      .map(|socket_address| {
         (
           socket_address,
           tokio::spawn(async move {
@@ -52,12 +56,12 @@ impl Service {
       }
     }
 
-    tracing::trace!("Found {:?} ips", matched_ips.len());
+    tracing::trace!("Found {:?} sockets", matched_ips.len());
 
     matched_ips
   }
 }
 
-pub(crate) fn to_socket(ip: IpAddr) -> SocketAddr {
-  SocketAddr::new(ip, 502)
+pub(crate) fn to_socket(ip: IpAddr, port: u16) -> SocketAddr {
+  SocketAddr::new(ip, port)
 }